@@ -0,0 +1,230 @@
+// in this file, we will define the access control of resources.
+// principals are who drive an intent: the resource it comes from, the user behind it
+// and the source of intent. a grant permit a principal to use some resources, and
+// optionally only some actions of them. an intent is permitted if any of its principals is.
+// acl is configured by json file ACL_FILE, for example:
+// {
+//     "open": false,
+//     "grants": [
+//         {"principal": {"Resource": "phone"}, "resources": ["*"]},
+//         {"principal": {"User": "guest"}, "resources": ["lamp", "heater"], "actions": ["turn_on"]},
+//         {"principal": {"Source": "Tape"}, "resources": ["*"]}
+//     ],
+//     "admins": [{"name": "alice", "token": "5b0e3c..."}]
+// }
+// without ACL_FILE, acl is open and every principal can use every resource.
+// if ACL_FILE can not be read, acl is closed and nothing is permitted until it is fixed.
+// admins can change rules of TAPE, they are known by their token, not by where the request comes
+// from, and only those listed are admins even if acl is open. keep ACL_FILE readable only by TAPE.
+
+use std::{fmt, fs, io::ErrorKind, path::Path};
+use lazy_static::lazy_static;
+use log::error;
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+
+use crate::base::{errort::BoxResult, intent::IntentSource};
+
+pub const ACL_FILE: &str = "acl.json";
+const ANY: &str = "*";
+
+lazy_static! {
+    pub static ref ACL: Mutex<Acl> = Mutex::new(Acl::load_or_closed(ACL_FILE));
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub enum Principal {
+    Resource(String),
+    User(String),
+    Source(IntentSource),
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Grant {
+    principal: Principal,
+    resources: Vec<String>,
+    // empty means all actions.
+    #[serde(default)]
+    actions: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Admin {
+    name: String,
+    token: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Acl {
+    // open acl permit everything, it is used when no acl is configured.
+    #[serde(default)]
+    open: bool,
+    #[serde(default)]
+    grants: Vec<Grant>,
+    #[serde(default)]
+    admins: Vec<Admin>,
+}
+
+// the permission which is missing when access is refused.
+#[derive(Debug, Clone)]
+pub struct Permission {
+    principals: Vec<Principal>,
+    resource: String,
+    action: Option<String>,
+}
+
+impl Grant {
+    pub fn new(principal: Principal, resources: Vec<String>, actions: Vec<String>) -> Self {
+        Self { principal, resources, actions }
+    }
+
+    fn permit(&self, principal: &Principal, resource: &str, action: Option<&str>) -> bool {
+        self.principal == *principal
+        && self.resources.iter().any(|r| r == ANY || r == resource)
+        && action.is_none_or(|a| self.actions.is_empty() || self.actions.iter().any(|g| g == ANY || g == a))
+    }
+}
+
+// compare in constant time, so that the token can not be guessed byte by byte.
+pub fn same_token(a: &str, b: &str) -> bool {
+    a.len() == b.len() && a.bytes().zip(b.bytes()).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+impl Admin {
+    pub fn new(name: String, token: String) -> Self {
+        Self { name, token }
+    }
+
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl Acl {
+    pub fn new(open: bool, grants: Vec<Grant>, admins: Vec<Admin>) -> Self {
+        Self { open, grants, admins }
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> BoxResult<Self> {
+        let data = fs::read_to_string(path)?;
+        Ok(serde_json::from_str(&data)?)
+    }
+
+    // open acl if the file is missing, closed one if it is broken, so that a typo never permit everything.
+    pub fn load_or_closed<P: AsRef<Path>>(path: P) -> Self {
+        let path = path.as_ref();
+        if fs::metadata(path).is_err_and(|e| e.kind() == ErrorKind::NotFound) {
+            return Self::default();
+        }
+        Self::load(path).unwrap_or_else(|e| {
+            error!("load acl from {} error: {}, nothing is permitted", path.display(), e);
+            Self::closed()
+        })
+    }
+
+    // acl permitting nothing, with no admin.
+    pub fn closed() -> Self {
+        Self { open: false, grants: Vec::new(), admins: Vec::new() }
+    }
+
+    pub fn grant(&mut self, grant: Grant) {
+        self.open = false;
+        self.grants.push(grant);
+    }
+
+    // check whether principals can use the resource, and the action if it is given.
+    pub fn check(&self, principals: &[Principal], resource: &str, action: Option<&str>) -> Result<(), Permission> {
+        if self.open {
+            return Ok(());
+        }
+        let permitted = principals.iter().any(|p| {
+            self.grants.iter().any(|g| g.permit(p, resource, action))
+        });
+        if permitted {
+            Ok(())
+        } else {
+            Err(Permission { principals: principals.to_vec(), resource: resource.to_string(), action: action.map(|a| a.to_string()) })
+        }
+    }
+
+    // the admin whose token is given, admins must be listed, open acl does not make anyone admin.
+    pub fn admin(&self, token: Option<&str>) -> Option<&Admin> {
+        let token = token.filter(|t| !t.is_empty())?;
+        self.admins.iter().find(|a| !a.token.is_empty() && same_token(&a.token, token))
+    }
+
+    // resources which are not permitted to the principals, with the missing permission.
+    pub fn check_all<'a>(&self, principals: &[Principal], resources: impl Iterator<Item = &'a String>) -> Vec<(String, Permission)> {
+        resources.filter_map(|r| self.check(principals, r, None).err().map(|p| (r.clone(), p))).collect()
+    }
+}
+
+impl Default for Acl {
+    fn default() -> Self {
+        Self { open: true, grants: Vec::new(), admins: Vec::new() }
+    }
+}
+
+impl fmt::Display for Principal {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Principal::Resource(r) => write!(f, "resource {r}"),
+            Principal::User(u) => write!(f, "user {u}"),
+            Principal::Source(s) => write!(f, "source {s:?}"),
+        }
+    }
+}
+
+// like `missing permission: user guest, source Resource -> heater.heat`.
+impl fmt::Display for Permission {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let principals = self.principals.iter().map(|p| p.to_string()).collect::<Vec<String>>().join(", ");
+        match &self.action {
+            Some(a) => write!(f, "missing permission: {} -> {}.{}", principals, self.resource, a),
+            None => write!(f, "missing permission: {} -> {}", principals, self.resource),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn phone() -> Vec<Principal> {
+        vec![Principal::Resource("phone".to_string())]
+    }
+
+    #[test]
+    fn missing_file_is_open_without_admin() {
+        let acl = Acl::load_or_closed("no-such-acl.json");
+        assert!(acl.check(&phone(), "lamp", None).is_ok());
+        assert!(acl.admin(Some("")).is_none());
+    }
+
+    #[test]
+    fn broken_file_permits_nothing() {
+        let path = std::env::temp_dir().join(format!("tape-acl-{}.json", std::process::id()));
+        fs::write(&path, r#"{"open": true, "admins": [{"name": "alice", "token": "secret"}],"#).unwrap();
+        let acl = Acl::load_or_closed(&path);
+        fs::remove_file(&path).unwrap();
+        assert!(acl.check(&phone(), "lamp", None).is_err());
+        assert!(acl.admin(Some("secret")).is_none());
+    }
+
+    #[test]
+    fn admins_are_known_by_token() {
+        let admins = vec![Admin::new("alice".to_string(), "secret".to_string()), Admin::new("bob".to_string(), "".to_string())];
+        let acl = Acl::new(true, vec![], admins);
+        let cases = [
+            (Some("secret"), Some("alice")),
+            (Some("secreT"), None),
+            (Some("secre"), None),
+            // admin without token is never matched.
+            (Some(""), None),
+            (None, None),
+        ];
+        for (token, want) in cases {
+            assert_eq!(acl.admin(token).map(|a| a.get_name()), want, "{:?}", token);
+        }
+    }
+}
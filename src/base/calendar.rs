@@ -0,0 +1,322 @@
+// in this file, we will define when rules are active: recurring time windows and holidays.
+// a window in json looks like:
+// {"days": ["Mon", "Tue", "Wed", "Thu", "Fri"], "from": "22:00:00", "to": "07:00:00", "timezone": "Europe/Berlin", "holidays": "Skip"}
+// days     empty means every day.
+// from, to local time of the window, from > to means it crosses midnight and belongs to the day
+//          it starts, so the window above is still active at 02:00 on Saturday. from == to means the whole day.
+// timezone IANA name, the local timezone of TAPE is used if it is not given.
+// holidays "Ignore" holidays are like other days, "Skip" the window is closed on holidays,
+//          "Only" the window is only open on holidays.
+// holidays are loaded from HOLIDAY_FILE in iCalendar format, every VEVENT is a holiday from its
+// DTSTART to DTEND, a single day if DTEND is not given. events with "RRULE:FREQ=YEARLY" repeat every year.
+
+use std::{fs, path::Path};
+use chrono::{DateTime, Datelike, FixedOffset, Local, NaiveDate, NaiveDateTime, NaiveTime, Utc, Weekday};
+use chrono_tz::Tz;
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+
+use crate::base::errort::BoxResult;
+
+pub const HOLIDAY_FILE: &str = "holidays.ics";
+
+lazy_static! {
+    pub static ref HOLIDAYS: Mutex<Holidays> = Mutex::new(Holidays::load(HOLIDAY_FILE).unwrap_or_default());
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum HolidayMode {
+    #[default]
+    Ignore,
+    Skip,
+    Only,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct TimeWindow {
+    #[serde(default)]
+    days: Vec<Weekday>,
+    from: NaiveTime,
+    to: NaiveTime,
+    #[serde(default)]
+    timezone: Option<Tz>,
+    #[serde(default)]
+    holidays: HolidayMode,
+}
+
+#[derive(Clone, Debug)]
+pub struct Holiday {
+    summary: String,
+    start: NaiveDate,
+    // the day after the holiday.
+    end: NaiveDate,
+    yearly: bool,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Holidays {
+    holidays: Vec<Holiday>,
+}
+
+impl TimeWindow {
+    pub fn new(days: Vec<Weekday>, from: NaiveTime, to: NaiveTime, timezone: Option<Tz>, holidays: HolidayMode) -> Self {
+        Self { days, from, to, timezone, holidays }
+    }
+
+    // the time in the timezone of the window.
+    pub fn in_timezone(&self, now: DateTime<Utc>) -> DateTime<FixedOffset> {
+        match self.timezone {
+            Some(tz) => now.with_timezone(&tz).fixed_offset(),
+            None => now.with_timezone(&Local).fixed_offset(),
+        }
+    }
+
+    // date and time in the timezone of the window.
+    fn local(&self, now: DateTime<Utc>) -> NaiveDateTime {
+        self.in_timezone(now).naive_local()
+    }
+
+    // whether the window may open on the day.
+    fn on_day(&self, day: NaiveDate, holidays: &Holidays) -> bool {
+        let weekday = self.days.is_empty() || self.days.contains(&day.weekday());
+        weekday && match self.holidays {
+            HolidayMode::Ignore => true,
+            HolidayMode::Skip => !holidays.contains(day),
+            HolidayMode::Only => holidays.contains(day),
+        }
+    }
+
+    pub fn is_active(&self, now: DateTime<Utc>, holidays: &Holidays) -> bool {
+        let local = self.local(now);
+        let (day, time) = (local.date(), local.time());
+        if self.from == self.to {
+            self.on_day(day, holidays)
+        } else if self.from < self.to {
+            self.from <= time && time < self.to && self.on_day(day, holidays)
+        } else if time >= self.from {
+            self.on_day(day, holidays)
+        } else if time < self.to {
+            day.pred_opt().is_some_and(|d| self.on_day(d, holidays))
+        } else {
+            false
+        }
+    }
+}
+
+impl Holiday {
+    pub fn get_summary(&self) -> &str {
+        &self.summary
+    }
+
+    fn contains(&self, day: NaiveDate) -> bool {
+        if !self.yearly {
+            return self.start <= day && day < self.end;
+        }
+        // the same holiday in the year of day, or the one of last year which may last over new year.
+        [day.year(), day.year() - 1].iter().any(|y| {
+            let start = match self.start.with_year(*y) {
+                Some(s) => s,
+                None => return false,
+            };
+            start <= day && day < start + (self.end - self.start)
+        })
+    }
+}
+
+// value of DTSTART or DTEND, only the date is used for date-time.
+fn ics_date(value: &str) -> Option<NaiveDate> {
+    let date = value.get(..8)?;
+    NaiveDate::parse_from_str(date, "%Y%m%d").ok()
+}
+
+impl Holidays {
+    pub fn load<P: AsRef<Path>>(path: P) -> BoxResult<Self> {
+        Ok(Self::parse(&fs::read_to_string(path)?))
+    }
+
+    // events without valid DTSTART are ignored.
+    pub fn parse(ics: &str) -> Self {
+        // long lines are folded by leading whitespace.
+        let mut lines: Vec<String> = vec![];
+        for l in ics.lines() {
+            match (l.strip_prefix([' ', '\t']), lines.last_mut()) {
+                (Some(rest), Some(last)) => last.push_str(rest),
+                _ => lines.push(l.trim_end().to_string()),
+            }
+        }
+
+        let mut holidays = vec![];
+        let mut event: Option<(String, Option<NaiveDate>, Option<NaiveDate>, bool)> = None;
+        for l in lines.iter() {
+            let (key, value) = match l.split_once(':') {
+                Some(kv) => kv,
+                None => continue,
+            };
+            // parameters like DTSTART;VALUE=DATE are not needed.
+            let name = key.split(';').next().unwrap_or_default().to_uppercase();
+            match (name.as_str(), event.as_mut()) {
+                ("BEGIN", _) if value.eq_ignore_ascii_case("VEVENT") => event = Some((String::new(), None, None, false)),
+                ("SUMMARY", Some(e)) => e.0 = value.to_string(),
+                ("DTSTART", Some(e)) => e.1 = ics_date(value),
+                ("DTEND", Some(e)) => e.2 = ics_date(value),
+                ("RRULE", Some(e)) => e.3 = value.to_uppercase().split(';').any(|p| p == "FREQ=YEARLY"),
+                ("END", Some(_)) if value.eq_ignore_ascii_case("VEVENT") => {
+                    if let Some((summary, Some(start), end, yearly)) = event.take() {
+                        let end = end.filter(|e| *e > start).or(start.succ_opt()).unwrap_or(start);
+                        holidays.push(Holiday { summary, start, end, yearly });
+                    }
+                },
+                _ => (),
+            }
+        }
+        Self { holidays }
+    }
+
+    pub fn contains(&self, day: NaiveDate) -> bool {
+        self.get(day).is_some()
+    }
+
+    pub fn get(&self, day: NaiveDate) -> Option<&Holiday> {
+        self.holidays.iter().find(|h| h.contains(day))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    const ICS: &str = "BEGIN:VCALENDAR\r
+BEGIN:VEVENT\r
+SUMMARY:Labour Day\r
+DTSTART;VALUE=DATE:20240501\r
+END:VEVENT\r
+BEGIN:VEVENT\r
+SUMMARY:Company\r
+  retreat\r
+DTSTART:20240610T090000Z\r
+DTEND:20240613T170000Z\r
+END:VEVENT\r
+BEGIN:VEVENT\r
+SUMMARY:New Year\r
+DTSTART;VALUE=DATE:20231231\r
+DTEND;VALUE=DATE:20240102\r
+RRULE:FREQ=YEARLY\r
+END:VEVENT\r
+BEGIN:VEVENT\r
+SUMMARY:no start\r
+DTEND;VALUE=DATE:20240701\r
+END:VEVENT\r
+END:VCALENDAR\r
+";
+
+    fn day(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    fn time(h: u32, m: u32) -> NaiveTime {
+        NaiveTime::from_hms_opt(h, m, 0).unwrap()
+    }
+
+    fn utc(y: i32, m: u32, d: u32, h: u32, min: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(y, m, d, h, min, 0).unwrap()
+    }
+
+    #[test]
+    fn parse_ics() {
+        let holidays = Holidays::parse(ICS);
+        assert_eq!(holidays.holidays.len(), 3);
+        let cases = [
+            (day(2024, 5, 1), Some("Labour Day")),
+            (day(2024, 5, 2), None),
+            // labour day is not yearly.
+            (day(2025, 5, 1), None),
+            // folded summary, end is not included.
+            (day(2024, 6, 10), Some("Company retreat")),
+            (day(2024, 6, 12), Some("Company retreat")),
+            (day(2024, 6, 13), None),
+            // yearly holiday lasting over new year.
+            (day(2023, 12, 31), Some("New Year")),
+            (day(2026, 1, 1), Some("New Year")),
+            (day(2026, 1, 2), None),
+            (day(2026, 12, 30), None),
+            (day(2024, 6, 30), None),
+        ];
+        for (d, want) in cases {
+            assert_eq!(holidays.get(d).map(|h| h.get_summary()), want, "{}", d);
+        }
+    }
+
+    #[test]
+    fn window_in_day_and_over_midnight() {
+        let holidays = Holidays::default();
+        let office = TimeWindow::new(vec![Weekday::Mon, Weekday::Fri], time(9, 0), time(17, 0), Some(Tz::UTC), HolidayMode::Ignore);
+        let night = TimeWindow::new(vec![Weekday::Fri], time(22, 0), time(7, 0), Some(Tz::UTC), HolidayMode::Ignore);
+        let all_day = TimeWindow::new(vec![Weekday::Sat], time(0, 0), time(0, 0), Some(Tz::UTC), HolidayMode::Ignore);
+        // 2024-05-03 is a Friday.
+        let cases = [
+            (&office, utc(2024, 5, 3, 9, 0), true),
+            (&office, utc(2024, 5, 3, 17, 0), false),
+            (&office, utc(2024, 5, 4, 12, 0), false),
+            (&night, utc(2024, 5, 3, 21, 59), false),
+            (&night, utc(2024, 5, 3, 22, 0), true),
+            // the night of Friday is still active on Saturday morning.
+            (&night, utc(2024, 5, 4, 2, 0), true),
+            (&night, utc(2024, 5, 4, 7, 0), false),
+            (&night, utc(2024, 5, 4, 22, 30), false),
+            (&night, utc(2024, 5, 3, 2, 0), false),
+            (&all_day, utc(2024, 5, 4, 0, 0), true),
+            (&all_day, utc(2024, 5, 4, 23, 59), true),
+            (&all_day, utc(2024, 5, 5, 0, 0), false),
+        ];
+        for (i, (w, now, want)) in cases.into_iter().enumerate() {
+            assert_eq!(w.is_active(now, &holidays), want, "case {} at {}", i, now);
+        }
+    }
+
+    #[test]
+    fn window_on_holidays() {
+        let holidays = Holidays::parse(ICS);
+        let window = |mode| TimeWindow::new(vec![], time(8, 0), time(20, 0), Some(Tz::UTC), mode);
+        let (holiday, workday) = (utc(2024, 5, 1, 12, 0), utc(2024, 5, 2, 12, 0));
+        let cases = [
+            (HolidayMode::Ignore, holiday, true),
+            (HolidayMode::Ignore, workday, true),
+            (HolidayMode::Skip, holiday, false),
+            (HolidayMode::Skip, workday, true),
+            (HolidayMode::Only, holiday, true),
+            (HolidayMode::Only, workday, false),
+        ];
+        for (mode, now, want) in cases {
+            assert_eq!(window(mode).is_active(now, &holidays), want, "{:?} at {}", mode, now);
+        }
+        // a night starting on a holiday is skipped as a whole.
+        let night = TimeWindow::new(vec![], time(22, 0), time(6, 0), Some(Tz::UTC), HolidayMode::Skip);
+        assert!(!night.is_active(utc(2024, 5, 2, 3, 0), &holidays));
+        assert!(night.is_active(utc(2024, 5, 3, 3, 0), &holidays));
+    }
+
+    #[test]
+    fn window_in_timezone() {
+        let holidays = Holidays::default();
+        let berlin = TimeWindow::new(vec![Weekday::Sun], time(1, 0), time(3, 0), Some(Tz::Europe__Berlin), HolidayMode::Ignore);
+        let cases = [
+            // Saturday 23:30 in UTC is Sunday 01:30 in Berlin in summer time.
+            (utc(2024, 5, 4, 23, 30), true),
+            (utc(2024, 5, 5, 1, 30), false),
+            // in winter time Berlin is only one hour ahead.
+            (utc(2024, 1, 6, 23, 30), false),
+            (utc(2024, 1, 7, 0, 30), true),
+            // clocks jump from 02:00 to 03:00 at 01:00 UTC of 2024-03-31.
+            (utc(2024, 3, 31, 0, 59), true),
+            (utc(2024, 3, 31, 1, 0), false),
+        ];
+        for (now, want) in cases {
+            assert_eq!(berlin.is_active(now, &holidays), want, "{}", now);
+        }
+        let local = berlin.in_timezone(utc(2024, 5, 4, 23, 30));
+        assert_eq!((local.weekday(), local.time()), (Weekday::Sun, time(1, 30)));
+    }
+}
@@ -0,0 +1,396 @@
+// in this file, we will define the capability manifest of resources.
+// a manifest declares what a resource can do in a machine readable way, so that
+// sub-intents can be validated instead of trusting the free-text description.
+// the LLM is only used to map the language of sub-intent onto a declared action.
+//
+// manifest in json looks like:
+// {
+//     "actions": [{
+//         "name": "heat",
+//         "description": "heat water to the given temperature",
+//         "params": [{"name": "temperature", "ptype": {"type": "Number", "min": 30.0, "max": 75.0}, "unit": "°C", "required": true}],
+//         "preconditions": ["Available"],
+//         "duration": {"secs": 300, "nanos": 0},
+//         "cost": {"energy": 500.0, "money": 0.2}
+//     }],
+//     "cost": {"energy": 5.0},
+//     "status": [
+//         {"name": "temperature", "ptype": {"type": "Number", "min": 0.0, "max": 100.0}, "unit": "°C"},
+//         {"name": "mode", "ptype": {"type": "Enum", "values": ["eco", "boost"]}}
+//     ]
+// }
+// status declares the custom fields the resource report in its status, undeclared or
+// mistyped fields are dropped when status is refreshed.
+// cost of an action is what it takes to execute it once, cost of the capability is used
+// for sub-intent bound to no action or to an action which declare no cost.
+
+use std::{fmt, ops::{Add, AddAssign}, time::Duration};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::base::resource::Status;
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct Capability {
+    actions: Vec<Action>,
+    #[serde(default)]
+    status: Vec<Param>,
+    #[serde(default)]
+    cost: Option<Cost>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Action {
+    name: String,
+    #[serde(default)]
+    description: String,
+    #[serde(default)]
+    params: Vec<Param>,
+    #[serde(default)]
+    preconditions: Vec<Precondition>,
+    // expected time to finish the action.
+    duration: Option<Duration>,
+    #[serde(default)]
+    cost: Option<Cost>,
+}
+
+// units are up to the deployment, like Wh for energy, cents for money and cycles for wear.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+pub struct Cost {
+    #[serde(default)]
+    pub energy: f64,
+    #[serde(default)]
+    pub money: f64,
+    #[serde(default)]
+    pub wear: f64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Param {
+    name: String,
+    ptype: ParamType,
+    unit: Option<String>,
+    #[serde(default)]
+    required: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(tag = "type")]
+pub enum ParamType {
+    Bool,
+    Integer { min: Option<i64>, max: Option<i64> },
+    Number { min: Option<f64>, max: Option<f64> },
+    String,
+    Enum { values: Vec<String> },
+}
+
+// preconditions are checked against the status of resource before routing.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub enum Precondition {
+    Available,
+    // resource is not dealing any intent.
+    Idle,
+    MaxDealing(u64),
+}
+
+// the action chosen for a sub-intent, it is what we send to the resource.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ActionCall {
+    pub action: String,
+    #[serde(default)]
+    pub params: Map<String, Value>,
+}
+
+impl Capability {
+    pub fn new(actions: Vec<Action>, status: Vec<Param>, cost: Option<Cost>) -> Self {
+        Self { actions, status, cost }
+    }
+
+    // whether the resource declare no action, status fields do not count.
+    pub fn is_empty(&self) -> bool {
+        self.actions.is_empty()
+    }
+
+    pub fn get_action(&self, name: &str) -> Option<&Action> {
+        self.actions.iter().find(|a| a.name == name)
+    }
+
+    pub fn iter_actions(&self) -> impl Iterator<Item = &Action> {
+        self.actions.iter()
+    }
+
+    pub fn get_status_field(&self, name: &str) -> Option<&Param> {
+        self.status.iter().find(|p| p.name == name)
+    }
+
+    pub fn iter_status_fields(&self) -> impl Iterator<Item = &Param> {
+        self.status.iter()
+    }
+
+    // drop fields of status which are not declared or mistyped, and tell why.
+    pub fn check_status(&self, status: &mut Status) -> Vec<String> {
+        let mut problems = vec![];
+        status.retain_fields(|k, v| {
+            let checked = match self.get_status_field(k) {
+                Some(p) => p.check(v),
+                None => Err(format!("status field `{}` is not declared", k)),
+            };
+            checked.map_err(|e| problems.push(e)).is_ok()
+        });
+        problems
+    }
+
+    // cost of executing the action once, or of using the resource if no action is given.
+    pub fn cost_of(&self, action: Option<&str>) -> Cost {
+        action.and_then(|a| self.get_action(a)).and_then(|a| a.cost)
+            .or(self.cost)
+            .unwrap_or_default()
+    }
+
+    // cost expected before we know which action will be bound.
+    pub fn expected_cost(&self) -> Cost {
+        if let Some(c) = self.cost {
+            return c;
+        }
+        let costs = self.actions.iter().filter_map(|a| a.cost).collect::<Vec<Cost>>();
+        if costs.is_empty() {
+            return Cost::default();
+        }
+        let n = costs.len() as f64;
+        let sum = costs.into_iter().fold(Cost::default(), |s, c| s + c);
+        Cost { energy: sum.energy / n, money: sum.money / n, wear: sum.wear / n }
+    }
+
+    // whether at least one action can be executed with the status now.
+    pub fn is_ready(&self, status: &Status) -> bool {
+        self.actions.iter().any(|a| a.check_preconditions(status).is_ok())
+    }
+
+    pub fn validate(&self, call: &ActionCall, status: &Status) -> Result<(), String> {
+        let action = match self.get_action(&call.action) {
+            Some(a) => a,
+            None => return Err(format!("no such action `{}`", call.action)),
+        };
+        action.check_preconditions(status)?;
+        action.check_params(&call.params)
+    }
+}
+
+impl Action {
+    pub fn new(name: String, description: String, params: Vec<Param>, preconditions: Vec<Precondition>, duration: Option<Duration>, cost: Option<Cost>) -> Self {
+        Self { name, description, params, preconditions, duration, cost }
+    }
+
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn get_duration(&self) -> Option<Duration> {
+        self.duration
+    }
+
+    fn check_preconditions(&self, status: &Status) -> Result<(), String> {
+        for p in self.preconditions.iter() {
+            let ok = match p {
+                Precondition::Available => status.get_aviliability(),
+                Precondition::Idle => status.get_dealing() == 0,
+                Precondition::MaxDealing(n) => status.get_dealing() < *n,
+            };
+            if !ok {
+                return Err(format!("precondition {:?} of `{}` is not satisfied", p, self.name));
+            }
+        }
+        Ok(())
+    }
+
+    fn check_params(&self, params: &Map<String, Value>) -> Result<(), String> {
+        for k in params.keys() {
+            if !self.params.iter().any(|p| &p.name == k) {
+                return Err(format!("`{}` has no parameter `{}`", self.name, k));
+            }
+        }
+        for p in self.params.iter() {
+            match params.get(&p.name) {
+                Some(v) => p.check(v)?,
+                None if p.required => return Err(format!("`{}` require parameter `{}`", self.name, p.name)),
+                None => (),
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Param {
+    pub fn new(name: String, ptype: ParamType, unit: Option<String>, required: bool) -> Self {
+        Self { name, ptype, unit, required }
+    }
+
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn get_unit(&self) -> Option<&str> {
+        self.unit.as_deref()
+    }
+
+    fn check(&self, v: &Value) -> Result<(), String> {
+        let ok = match &self.ptype {
+            ParamType::Bool => v.is_boolean(),
+            ParamType::String => v.is_string(),
+            ParamType::Integer { min, max } => match v.as_i64() {
+                Some(i) => min.is_none_or(|m| i >= m) && max.is_none_or(|m| i <= m),
+                None => false,
+            },
+            ParamType::Number { min, max } => match v.as_f64() {
+                Some(f) => min.is_none_or(|m| f >= m) && max.is_none_or(|m| f <= m),
+                None => false,
+            },
+            ParamType::Enum { values } => match v.as_str() {
+                Some(s) => values.iter().any(|e| e == s),
+                None => false,
+            },
+        };
+        if ok {
+            Ok(())
+        } else {
+            Err(format!("parameter `{}` should be {}, but get {}", self.name, self.ptype, v))
+        }
+    }
+}
+
+impl Add for Cost {
+    type Output = Cost;
+
+    fn add(self, other: Cost) -> Cost {
+        Cost { energy: self.energy + other.energy, money: self.money + other.money, wear: self.wear + other.wear }
+    }
+}
+
+impl AddAssign for Cost {
+    fn add_assign(&mut self, other: Cost) {
+        *self = *self + other;
+    }
+}
+
+impl fmt::Display for ParamType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fn bound<T: fmt::Display>(b: &Option<T>) -> String {
+            b.as_ref().map(|b| b.to_string()).unwrap_or_default()
+        }
+        match self {
+            ParamType::Bool => write!(f, "bool"),
+            ParamType::String => write!(f, "string"),
+            ParamType::Integer { min, max } => write!(f, "integer[{}..{}]", bound(min), bound(max)),
+            ParamType::Number { min, max } => write!(f, "number[{}..{}]", bound(min), bound(max)),
+            ParamType::Enum { values } => write!(f, "one of {}", values.join("|")),
+        }
+    }
+}
+
+// compact form used in prompts, like `heat(temperature: number[30..75] °C)`.
+impl fmt::Display for Capability {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let actions = self.actions.iter().map(|a| {
+            let params = a.params.iter().map(|p| {
+                let optional = if p.required { "" } else { "?" };
+                let unit = p.unit.as_ref().map(|u| format!(" {u}")).unwrap_or_default();
+                format!("{}{}: {}{}", p.name, optional, p.ptype, unit)
+            }).collect::<Vec<String>>().join(", ");
+            format!("{}({})", a.name, params)
+        }).collect::<Vec<String>>();
+        write!(f, "{}", actions.join(", "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn heater() -> Capability {
+        serde_json::from_value(json!({
+            "actions": [{
+                "name": "heat",
+                "params": [
+                    {"name": "temperature", "ptype": {"type": "Number", "min": 30.0, "max": 75.0}, "unit": "°C", "required": true},
+                    {"name": "minutes", "ptype": {"type": "Integer", "min": 1, "max": null}, "unit": null},
+                    {"name": "mode", "ptype": {"type": "Enum", "values": ["eco", "boost"]}, "unit": null}
+                ],
+                "preconditions": ["Available", {"MaxDealing": 2}],
+                "duration": null,
+                "cost": {"energy": 500.0}
+            }, {
+                "name": "stop",
+                "preconditions": ["Idle"],
+                "duration": null
+            }],
+            "status": [
+                {"name": "temperature", "ptype": {"type": "Number", "min": 0.0, "max": 100.0}, "unit": "°C"},
+                {"name": "locked", "ptype": {"type": "Bool"}, "unit": null}
+            ],
+            "cost": null
+        })).unwrap()
+    }
+
+    fn status(available: bool, dealing: u64) -> Status {
+        let mut s = Status::new(available, (0.0, 0.0, 0.0), Duration::from_secs(0));
+        for _ in 0..dealing {
+            s.change_dealing(true);
+        }
+        s
+    }
+
+    #[test]
+    fn validate_calls() {
+        let capability = heater();
+        let cases = [
+            ("heat", json!({"temperature": 60}), status(true, 0), Ok(())),
+            ("heat", json!({"temperature": 30.0, "minutes": 5, "mode": "eco"}), status(true, 1), Ok(())),
+            ("heat", json!({"temperature": 80}), status(true, 0), Err("should be number[30..75]")),
+            ("heat", json!({"temperature": "hot"}), status(true, 0), Err("should be number")),
+            ("heat", json!({"temperature": 60, "minutes": 0}), status(true, 0), Err("should be integer[1..]")),
+            ("heat", json!({"temperature": 60, "minutes": 1.5}), status(true, 0), Err("should be integer")),
+            ("heat", json!({"temperature": 60, "mode": "turbo"}), status(true, 0), Err("should be one of eco|boost")),
+            ("heat", json!({"minutes": 5}), status(true, 0), Err("require parameter `temperature`")),
+            ("heat", json!({"temperature": 60, "colour": "red"}), status(true, 0), Err("has no parameter `colour`")),
+            ("heat", json!({"temperature": 60}), status(false, 0), Err("precondition Available")),
+            ("heat", json!({"temperature": 60}), status(true, 2), Err("precondition MaxDealing(2)")),
+            ("stop", json!({}), status(false, 0), Ok(())),
+            ("stop", json!({}), status(true, 1), Err("precondition Idle")),
+            ("cool", json!({}), status(true, 0), Err("no such action `cool`")),
+        ];
+        for (action, params, status, want) in cases {
+            let call = ActionCall { action: action.to_string(), params: params.as_object().unwrap().clone() };
+            match (capability.validate(&call, &status), want) {
+                (Ok(()), Ok(())) => (),
+                (Err(e), Err(w)) => assert!(e.contains(w), "{} {}: `{}` does not contain `{}`", action, params, e, w),
+                (got, want) => panic!("{} {}: got {:?}, want {:?}", action, params, got, want),
+            }
+        }
+    }
+
+    #[test]
+    fn undeclared_and_mistyped_status_fields_are_dropped() {
+        let mut s = status(true, 0);
+        s.set_field("temperature".to_string(), json!(42.5));
+        s.set_field("locked".to_string(), json!("yes"));
+        s.set_field("colour".to_string(), json!("red"));
+        let problems = heater().check_status(&mut s);
+        assert_eq!(problems.len(), 2);
+        assert_eq!(s.get_field("temperature"), Some(&json!(42.5)));
+        assert!(s.get_field("locked").is_none());
+        assert!(s.get_field("colour").is_none());
+    }
+
+    #[test]
+    fn cost_of_actions() {
+        let capability = heater();
+        assert_eq!(capability.cost_of(Some("heat")).energy, 500.0);
+        // stop declares no cost and the capability neither.
+        assert_eq!(capability.cost_of(Some("stop")), Cost::default());
+        assert_eq!(capability.expected_cost().energy, 500.0);
+        assert!(capability.is_ready(&status(false, 0)));
+        assert!(!capability.is_ready(&status(false, 1)));
+    }
+}
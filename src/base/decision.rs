@@ -0,0 +1,100 @@
+// in this file, we will explain why TAPE rejects an intent.
+// every rule judged in preprocess leaves a decision. a rejected intent is answered by a Reject
+// message whose body tells the reason in words, and whose `m_explanation` is the explanation in
+// json, which is also sent with the Rejected event:
+// {
+//     "intent": "turn on the oven",
+//     "reason": "We do not accept intent when time in 22:00..06:00.",
+//     "decided_by": {"id": 3, "name": "quiet night", "verdict": "Deny", "reason": "..."},
+//     "rules": [{"id": 1, "name": "safety", "verdict": "Pass", "reason": null}, ...]
+// }
+// verdict of a rule is one of:
+// Inactive  the rule is out of its time window, it is not judged.
+// Pass      the rule does not match the intent.
+// Deny      the rule matches and it is a deny rule, reason tells why.
+// Allow     the rule matches and it is an allow rule.
+
+use serde::{Deserialize, Serialize};
+
+use crate::base::rule::{Effect, Rule};
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Verdict {
+    Inactive,
+    Pass,
+    Deny,
+    Allow,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct RuleDecision {
+    id: i64,
+    name: String,
+    verdict: Verdict,
+    // why the rule matches, with the rationale of model for prompt rules.
+    reason: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Explanation {
+    intent: String,
+    reason: String,
+    // None if the intent is not rejected by a rule.
+    decided_by: Option<RuleDecision>,
+    rules: Vec<RuleDecision>,
+}
+
+impl RuleDecision {
+    // decision of the rule, reason is given if it matches.
+    pub fn new(rule: &Rule, active: bool, reason: Option<String>) -> Self {
+        let verdict = match (active, &reason, rule.get_effect()) {
+            (false, _, _) => Verdict::Inactive,
+            (true, None, _) => Verdict::Pass,
+            (true, Some(_), Effect::Deny) => Verdict::Deny,
+            (true, Some(_), Effect::Allow) => Verdict::Allow,
+        };
+        Self { id: rule.get_id(), name: rule.get_name().to_string(), verdict, reason }
+    }
+
+    pub fn get_id(&self) -> i64 {
+        self.id
+    }
+
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn get_verdict(&self) -> Verdict {
+        self.verdict
+    }
+
+    pub fn get_reason(&self) -> Option<&str> {
+        self.reason.as_deref()
+    }
+}
+
+impl Explanation {
+    pub fn new(intent: &str, reason: &str, decided_by: Option<RuleDecision>, rules: Vec<RuleDecision>) -> Self {
+        Self { intent: intent.to_string(), reason: reason.to_string(), decided_by, rules }
+    }
+
+    pub fn get_reason(&self) -> &str {
+        &self.reason
+    }
+
+    pub fn get_decided_by(&self) -> Option<&RuleDecision> {
+        self.decided_by.as_ref()
+    }
+
+    pub fn get_rules(&self) -> &[RuleDecision] {
+        &self.rules
+    }
+
+    // reason of rejection in one line, with the deciding rule.
+    pub fn summary(&self) -> String {
+        match &self.decided_by {
+            Some(d) => format!("rule {} ({}): {}", d.id, d.name, self.reason),
+            None => self.reason.clone(),
+        }
+    }
+}
@@ -48,3 +48,23 @@ impl Error for RouteError {
         &self.details
     }
 }
+
+// error of interpreter which translate intent into device command.
+#[derive(Debug)]
+pub enum InterpretError {
+    // the resource refuse the intent.
+    Rejected(String),
+    // the interpreter can not give a command.
+    Failed(String),
+}
+
+impl fmt::Display for InterpretError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            InterpretError::Rejected(r) => write!(f, "rejected by interpreter: {}", r),
+            InterpretError::Failed(r) => write!(f, "interpreter failed: {}", r),
+        }
+    }
+}
+
+impl Error for InterpretError {}
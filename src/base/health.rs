@@ -0,0 +1,285 @@
+// in this file, we will track the health of resources and break the circuit of bad ones.
+// every routed sub-intent ends in success, failure(rejected or send error) or timeout,
+// and the outcome is recorded for the resource it routed to.
+// circuit of resource:
+// Closed   -> Open      failure rate of recent outcomes cross FAILURE_THRESHOLD.
+// Open     -> HalfOpen  after OPEN_D, one trial intent is allowed.
+// HalfOpen -> Closed    trial intent success.
+// HalfOpen -> Open      trial intent fail or timeout.
+
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use lazy_static::lazy_static;
+use log::warn;
+use tokio::sync::Mutex;
+
+// number of recent outcomes used to calculate failure rate.
+const WINDOW: usize = 20;
+// circuit will not open before we have enough outcomes.
+const MIN_SAMPLES: usize = 5;
+const FAILURE_THRESHOLD: f32 = 0.5;
+const OPEN_D: Duration = Duration::from_secs(30);
+
+lazy_static! {
+    pub static ref HEALTH: Arc<Mutex<HealthBook>> = Arc::new(Mutex::new(HealthBook::new()));
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Circuit {
+    Closed,
+    Open(Instant),
+    // true if the trial intent is in flight.
+    HalfOpen(bool),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    Success,
+    Failure,
+    Timeout,
+}
+
+#[derive(Debug, Clone)]
+pub struct Health {
+    success: u64,
+    failure: u64,
+    timeout: u64,
+    // moving average of time between routed and completed.
+    latency: Option<Duration>,
+    recent: VecDeque<Outcome>,
+    circuit: Circuit,
+}
+
+pub struct HealthBook {
+    resources: HashMap<String, Health>,
+    // sub-intent id -> resource and the time it is routed.
+    inflight: HashMap<i64, (String, Instant)>,
+}
+
+impl Health {
+    fn new() -> Self {
+        Self { success: 0, failure: 0, timeout: 0, latency: None, recent: VecDeque::new(), circuit: Circuit::Closed }
+    }
+
+    pub fn get_success(&self) -> u64 {
+        self.success
+    }
+
+    pub fn get_failure(&self) -> u64 {
+        self.failure
+    }
+
+    pub fn get_timeout(&self) -> u64 {
+        self.timeout
+    }
+
+    pub fn get_latency(&self) -> Option<Duration> {
+        self.latency
+    }
+
+    pub fn get_circuit(&self) -> Circuit {
+        self.circuit
+    }
+
+    pub fn failure_rate(&self) -> f32 {
+        if self.recent.is_empty() {
+            return 0.0;
+        }
+        let bad = self.recent.iter().filter(|o| **o != Outcome::Success).count();
+        bad as f32 / self.recent.len() as f32
+    }
+
+    // score between 0 and 1, higher is healthier.
+    pub fn score(&self) -> f32 {
+        match self.circuit {
+            Circuit::Open(_) => 0.0,
+            _ => 1.0 - self.failure_rate(),
+        }
+    }
+
+    fn record(&mut self, outcome: Outcome, latency: Option<Duration>) {
+        match outcome {
+            Outcome::Success => self.success += 1,
+            Outcome::Failure => self.failure += 1,
+            Outcome::Timeout => self.timeout += 1,
+        }
+        if let Some(l) = latency {
+            self.latency = Some(match self.latency {
+                Some(a) => a.mul_f32(0.8) + l.mul_f32(0.2),
+                None => l,
+            });
+        }
+        self.recent.push_back(outcome);
+        if self.recent.len() > WINDOW {
+            self.recent.pop_front();
+        }
+
+        self.circuit = match (self.circuit, outcome) {
+            (Circuit::HalfOpen(_), Outcome::Success) => {
+                self.recent.clear();
+                Circuit::Closed
+            },
+            (Circuit::HalfOpen(_), _) => Circuit::Open(Instant::now()),
+            (Circuit::Closed, _) if self.recent.len() >= MIN_SAMPLES && self.failure_rate() >= FAILURE_THRESHOLD => {
+                Circuit::Open(Instant::now())
+            },
+            (c, _) => c,
+        };
+    }
+
+    // whether a new intent can be routed to the resource now.
+    fn is_available(&mut self) -> bool {
+        match self.circuit {
+            Circuit::Closed => true,
+            Circuit::Open(since) => {
+                if since.elapsed() < OPEN_D {
+                    return false;
+                }
+                self.circuit = Circuit::HalfOpen(false);
+                true
+            },
+            Circuit::HalfOpen(trial) => !trial,
+        }
+    }
+}
+
+impl HealthBook {
+    fn new() -> Self {
+        Self { resources: HashMap::new(), inflight: HashMap::new() }
+    }
+
+    pub fn get_health(&self, name: &str) -> Option<&Health> {
+        self.resources.get(name)
+    }
+
+    pub fn is_available(&mut self, name: &str) -> bool {
+        match self.resources.get_mut(name) {
+            Some(h) => h.is_available(),
+            None => true,
+        }
+    }
+
+    pub fn score(&self, name: &str) -> f32 {
+        self.resources.get(name).map(|h| h.score()).unwrap_or(1.0)
+    }
+
+    pub fn routed(&mut self, name: &str, sub_id: i64) {
+        let h = self.resources.entry(name.to_string()).or_insert_with(Health::new);
+        if let Circuit::HalfOpen(_) = h.circuit {
+            h.circuit = Circuit::HalfOpen(true);
+        }
+        self.inflight.insert(sub_id, (name.to_string(), Instant::now()));
+    }
+
+    // record the outcome of a routed sub-intent.
+    pub fn complete(&mut self, sub_id: i64, outcome: Outcome) {
+        let (name, routed) = match self.inflight.remove(&sub_id) {
+            Some(r) => r,
+            None => return,
+        };
+        let latency = match outcome {
+            Outcome::Success => Some(routed.elapsed()),
+            _ => None,
+        };
+        self.record(&name, outcome, latency);
+    }
+
+    // record the outcome which is not bind to a sub-intent, like send error.
+    pub fn record(&mut self, name: &str, outcome: Outcome, latency: Option<Duration>) {
+        let h = self.resources.entry(name.to_string()).or_insert_with(Health::new);
+        let closed = !matches!(h.circuit, Circuit::Open(_));
+        h.record(outcome, latency);
+        if closed && matches!(h.circuit, Circuit::Open(_)) {
+            warn!("circuit of {} open, failure rate {}", name, h.failure_rate());
+        }
+    }
+
+    pub fn remove(&mut self, name: &str) {
+        self.resources.remove(name);
+        self.inflight.retain(|_, (n, _)| n != name);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use Outcome::{Failure as F, Success as S, Timeout as T};
+
+    fn state(c: Circuit) -> &'static str {
+        match c {
+            Circuit::Closed => "closed",
+            Circuit::Open(_) => "open",
+            Circuit::HalfOpen(_) => "half open",
+        }
+    }
+
+    // pretend the circuit of the resource has been open for OPEN_D.
+    fn expire(book: &mut HealthBook, name: &str) {
+        let h = book.resources.get_mut(name).unwrap();
+        h.circuit = Circuit::Open(Instant::now().checked_sub(OPEN_D).unwrap());
+    }
+
+    #[test]
+    fn circuit_opens_on_failure_rate() {
+        let cases: [(&[Outcome], &str); 6] = [
+            (&[F, F, F, F], "closed"),
+            (&[F, F, F, F, F], "open"),
+            (&[T, F, T, F, T], "open"),
+            (&[S, F, S, F, S], "closed"),
+            (&[S, F, S, F, S, F], "open"),
+            (&[S, S, S, S, S, S, S, S, S, S, S, F, F, F, F, F, F, F, F, F], "closed"),
+        ];
+        for (outcomes, want) in cases {
+            let mut book = HealthBook::new();
+            for o in outcomes {
+                book.record("lamp", *o, None);
+            }
+            assert_eq!(state(book.get_health("lamp").unwrap().get_circuit()), want, "{:?}", outcomes);
+        }
+    }
+
+    #[test]
+    fn half_open_allows_one_trial() {
+        for (trial, want) in [(S, "closed"), (F, "open"), (T, "open")] {
+            let mut book = HealthBook::new();
+            for _ in 0..MIN_SAMPLES {
+                book.record("lamp", F, None);
+            }
+            assert!(!book.is_available("lamp"));
+            assert_eq!(book.score("lamp"), 0.0);
+
+            expire(&mut book, "lamp");
+            assert!(book.is_available("lamp"));
+            assert_eq!(book.get_health("lamp").unwrap().get_circuit(), Circuit::HalfOpen(false));
+            book.routed("lamp", 1);
+            assert!(!book.is_available("lamp"), "only one trial is in flight");
+
+            book.complete(1, trial);
+            let h = book.get_health("lamp").unwrap();
+            assert_eq!(state(h.get_circuit()), want, "{:?}", trial);
+            if trial == S {
+                // the failures before the trial are forgotten.
+                assert_eq!(h.failure_rate(), 0.0);
+                assert!(h.get_latency().is_some());
+                assert!(book.is_available("lamp"));
+            } else {
+                assert!(!book.is_available("lamp"));
+            }
+        }
+    }
+
+    #[test]
+    fn outcomes_of_unknown_intents_are_ignored() {
+        let mut book = HealthBook::new();
+        assert!(book.is_available("lamp"));
+        assert_eq!(book.score("lamp"), 1.0);
+        book.routed("lamp", 1);
+        book.complete(2, F);
+        book.remove("lamp");
+        book.complete(1, F);
+        assert!(book.get_health("lamp").is_none());
+    }
+}
@@ -2,7 +2,13 @@
 use std::time::Instant;
 use serde::{Deserialize, Serialize};
 
-use crate::tools::idgen::{self, IdType};
+use crate::{
+    base::{acl::Principal, capability::{ActionCall, Cost}, decision::Explanation, resource::Place},
+    tools::idgen::{self, IdType},
+};
+
+// priority of emergency intent, other intents have priority 0 unless it is given.
+pub const EMERGENCY_PRIORITY: i32 = 100;
 
 // raw intent format is "Intent:intent_description"
 // the intent struct is not used for sending between outside and inside the system.
@@ -17,9 +23,18 @@ pub struct Intent {
     sub_intent: Vec<SubIntent>,
     reject_reason: Option<String>,
     emergency: bool,
+    priority: i32,
+    // area the intent is limited to.
+    place: Option<Place>,
+    // user who drive the intent, if it is known.
+    user: Option<String>,
+    // allow rule which accept the intent, rules before it are judged again with target when routing.
+    allowed_by: Option<i64>,
+    // how rules decide to reject the intent.
+    explanation: Option<Explanation>,
 }
 
-#[derive(PartialEq, Eq, Serialize, Deserialize)]
+#[derive(PartialEq, Eq, Serialize, Deserialize, Clone, Debug)]
 pub enum IntentSource {
     Tape,
     Input,
@@ -41,6 +56,13 @@ pub struct SubIntent {
     complete: bool,
     available_resources: Vec<String>,
     selected_resource: Option<String>,
+    // action of the selected resource's capability which the sub-intent is mapped onto.
+    action: Option<ActionCall>,
+    // cost of executing the sub-intent by the selected resource.
+    cost: Option<Cost>,
+    place: Option<Place>,
+    // principals of the intent, used to check access of resources.
+    principals: Vec<Principal>,
     routed: Instant,
 }
 
@@ -48,6 +70,7 @@ impl Intent {
     pub fn new(description: String, source: IntentSource, itype: IntentType, resource: Option<String>) -> Self {
         Self { 
             id: idgen::generate_id(idgen::IdType::Intent),
+            place: Place::from_description(&description),
             description, 
             complete: false, 
             source, 
@@ -56,17 +79,34 @@ impl Intent {
             sub_intent: vec![], 
             reject_reason: None,
             emergency: false,
+            priority: 0,
+            user: None,
+            allowed_by: None,
+            explanation: None,
         }
     }
 
     pub fn set_emergency(&mut self) {
         self.emergency = true;
+        self.priority = self.priority.max(EMERGENCY_PRIORITY);
     }
 
     pub fn get_emergency(&self) -> bool {
         self.emergency
     }
 
+    pub fn get_place(&self) -> Option<&Place> {
+        self.place.as_ref()
+    }
+
+    pub fn get_priority(&self) -> i32 {
+        self.priority
+    }
+
+    pub fn set_priority(&mut self, priority: i32) {
+        self.priority = priority;
+    }
+
     pub fn get_id(&self) -> i64 {
         self.id
     }
@@ -100,6 +140,11 @@ impl Intent {
         self.complete = true;
     }
 
+    // total cost of sub-intents, sub-intent routed to resource declaring no cost count as zero.
+    pub fn get_cost(&self) -> Cost {
+        self.sub_intent.iter().filter_map(|s| s.cost).fold(Cost::default(), |sum, c| sum + c)
+    }
+
     pub fn add_sub_intent(&mut self, sub_intent: Vec<SubIntent>) {
         self.sub_intent.extend(sub_intent);
     }
@@ -112,6 +157,45 @@ impl Intent {
         self.reject_reason.clone()
     }
 
+    pub fn set_reject_reason(&mut self, reason: String) {
+        self.reject_reason = Some(reason);
+    }
+
+    pub fn get_user(&self) -> Option<&String> {
+        self.user.as_ref()
+    }
+
+    pub fn set_user(&mut self, user: String) {
+        self.user = Some(user);
+    }
+
+    pub fn get_allowed_by(&self) -> Option<i64> {
+        self.allowed_by
+    }
+
+    pub fn set_allowed_by(&mut self, id: Option<i64>) {
+        self.allowed_by = id;
+    }
+
+    pub fn get_explanation(&self) -> Option<&Explanation> {
+        self.explanation.as_ref()
+    }
+
+    pub fn set_explanation(&mut self, explanation: Explanation) {
+        self.explanation = Some(explanation);
+    }
+
+    pub fn principals(&self) -> Vec<Principal> {
+        let mut principals = vec![Principal::Source(self.source.clone())];
+        if let Some(r) = &self.resource {
+            principals.push(Principal::Resource(r.clone()));
+        }
+        if let Some(u) = &self.user {
+            principals.push(Principal::User(u.clone()));
+        }
+        principals
+    }
+
     pub fn get_intent_type(&self) -> &IntentType {
         &self.itype
     }
@@ -120,7 +204,7 @@ impl Intent {
 
 impl SubIntent {
     pub fn new(description: String, available_resources: Vec<String>) -> Self {
-        Self {id: idgen::generate_id(IdType::Intent), description, complete: false, available_resources, selected_resource: None, routed: Instant::now()}
+        Self {id: idgen::generate_id(IdType::Intent), description, complete: false, available_resources, selected_resource: None, action: None, cost: None, place: None, principals: vec![], routed: Instant::now()}
     }
 
     pub fn get_id(&self) -> i64 {
@@ -159,6 +243,38 @@ impl SubIntent {
         &self.description
     }
 
+    pub fn get_place(&self) -> Option<&Place> {
+        self.place.as_ref()
+    }
+
+    pub fn set_place(&mut self, place: Option<Place>) {
+        self.place = place;
+    }
+
+    pub fn get_principals(&self) -> &[Principal] {
+        &self.principals
+    }
+
+    pub fn set_principals(&mut self, principals: Vec<Principal>) {
+        self.principals = principals;
+    }
+
+    pub fn get_action(&self) -> Option<&ActionCall> {
+        self.action.as_ref()
+    }
+
+    pub fn set_action(&mut self, action: Option<ActionCall>) {
+        self.action = action;
+    }
+
+    pub fn get_cost(&self) -> Option<Cost> {
+        self.cost
+    }
+
+    pub fn set_cost(&mut self, cost: Option<Cost>) {
+        self.cost = cost;
+    }
+
     pub fn is_complete(&self) -> bool {
         self.complete
     }
@@ -2,13 +2,21 @@
 use std::fmt::{self, Display};
 use serde::{Serialize, Deserialize};
 
+use crate::base::decision::Explanation;
+
 #[derive(Serialize, Deserialize)]
 pub struct Message {
     m_type: MessageType,
 
     m_body: String,    
-    // in actual, this is id of intent.
+    // in actual, this is id of intent, or id of resource for register.
     m_id: Option<i64>,
+    // token proving the id of resource is ours, see owner, or the admin token of acl for Admin messages.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    m_token: Option<String>,
+    // why rules reject the intent, only in Reject messages, the body keeps the reason in words.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    m_explanation: Option<Explanation>,
 }
 
 #[derive(Serialize, Deserialize, PartialEq, Eq)]
@@ -20,6 +28,10 @@ pub enum MessageType {
     Register,
     Heartbeat,
     Status,
+    // TAPE hand the resource over to another TAPE, body is `RegisterServer` json of it.
+    Handover,
+    // manage rules of TAPE, body is `AdminRequest` json, see ruleadmin.
+    Admin,
     Unknown,
 }
 
@@ -33,6 +45,8 @@ impl Display for MessageType {
             MessageType::Register => write!(f, "Register"),
             MessageType::Heartbeat => write!(f, "Heartbeat"),
             MessageType::Status => write!(f, "Status"),
+            MessageType::Handover => write!(f, "Handover"),
+            MessageType::Admin => write!(f, "Admin"),
             MessageType::Unknown => write!(f, "Unknown"),
         }
     }
@@ -44,9 +58,29 @@ impl Message {
             m_type,
             m_body,
             m_id,
+            m_token: None,
+            m_explanation: None,
         }
     }
 
+    pub fn with_token(mut self, token: Option<String>) -> Self {
+        self.m_token = token;
+        self
+    }
+
+    pub fn get_token(&self) -> Option<&str> {
+        self.m_token.as_deref()
+    }
+
+    pub fn with_explanation(mut self, explanation: Option<Explanation>) -> Self {
+        self.m_explanation = explanation;
+        self
+    }
+
+    pub fn get_explanation(&self) -> Option<&Explanation> {
+        self.m_explanation.as_ref()
+    }
+
     pub fn get_type(&self) -> &MessageType {
         &self.m_type
     }
@@ -0,0 +1,94 @@
+// in this file, we will define what the router optimize for users.
+// the router choose the resource with the least weighted objective among the candidates:
+// load    how busy and unhealthy the resource is, which is what the router used to consider only.
+// time    expected time to finish, including the intents it is dealing.
+// energy, money, wear  expected cost declared by the capability of resource.
+// every term is normalized among the candidates, so weights only tell how much we care.
+// preferences are configured by json file PREFERENCE_FILE, for example:
+// {
+//     "default": "Balanced",
+//     "users": {
+//         "alice": "Cheapest",
+//         "bob": {"Custom": {"load": 0.5, "time": 1.0, "energy": 2.0}}
+//     }
+// }
+
+use std::{collections::HashMap, fs, path::Path};
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+
+use crate::base::errort::BoxResult;
+
+pub const PREFERENCE_FILE: &str = "preference.json";
+// load still matters a little when optimizing others, so that equal resources are used in turn.
+const TIE_LOAD: f64 = 0.2;
+
+lazy_static! {
+    pub static ref PREFERENCES: Mutex<Preferences> = Mutex::new(Preferences::load(PREFERENCE_FILE).unwrap_or_default());
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default)]
+pub struct Weights {
+    #[serde(default)]
+    pub load: f64,
+    #[serde(default)]
+    pub time: f64,
+    #[serde(default)]
+    pub energy: f64,
+    #[serde(default)]
+    pub money: f64,
+    #[serde(default)]
+    pub wear: f64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default)]
+pub enum Objective {
+    #[default]
+    Balanced,
+    Fastest,
+    Cheapest,
+    Greenest,
+    Custom(Weights),
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct Preferences {
+    #[serde(default)]
+    default: Objective,
+    #[serde(default)]
+    users: HashMap<String, Objective>,
+}
+
+impl Objective {
+    pub fn weights(&self) -> Weights {
+        let w = Weights::default();
+        match *self {
+            Objective::Balanced => Weights { load: 1.0, ..w },
+            Objective::Fastest => Weights { load: TIE_LOAD, time: 1.0, ..w },
+            Objective::Cheapest => Weights { load: TIE_LOAD, money: 1.0, ..w },
+            Objective::Greenest => Weights { load: TIE_LOAD, energy: 1.0, ..w },
+            Objective::Custom(w) => w,
+        }
+    }
+}
+
+impl Preferences {
+    pub fn load<P: AsRef<Path>>(path: P) -> BoxResult<Self> {
+        let data = fs::read_to_string(path)?;
+        Ok(serde_json::from_str(&data)?)
+    }
+
+    // objective of the user, default one for unknown user or intent without user.
+    pub fn get(&self, user: Option<&str>) -> Objective {
+        user.and_then(|u| self.users.get(u)).copied().unwrap_or(self.default)
+    }
+
+    pub fn set(&mut self, user: String, objective: Objective) {
+        self.users.insert(user, objective);
+    }
+
+    pub fn set_default(&mut self, objective: Objective) {
+        self.default = objective;
+    }
+}
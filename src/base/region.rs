@@ -0,0 +1,241 @@
+// in this file, we will define the service region of TAPE.
+// a TAPE only serve resources inside its region, and resource moving out of it
+// will be handed over to the TAPE responsible for its new position.
+// region is configured by json file REGION_FILE, for example:
+// [
+//     {"name": "hall", "priority": 0, "shape": {"Box": {"min": [-100.0, -100.0, -100.0], "max": [100.0, 100.0, 100.0]}}},
+//     {"name": "lab", "priority": 1, "shape": {"Polygon": {"points": [[0.0, 0.0], [10.0, 0.0], [5.0, 8.0]], "z": [0.0, 3.0]}}},
+//     {"name": "302", "priority": 2, "shape": {"Zone": {"site": null, "building": "A", "floor": "3", "room": "302"}}}
+// ]
+// regions can overlap, the one with higher priority, then the smaller one, is more suitable.
+
+use std::{cmp::Ordering, fs, path::Path};
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+
+use crate::base::{
+    errort::BoxResult,
+    resource::{Place, Position},
+};
+
+pub const REGION_FILE: &str = "region.json";
+
+lazy_static! {
+    pub static ref SERVICE_AREA: Mutex<ServiceArea> = Mutex::new(ServiceArea::load(REGION_FILE).unwrap_or_default());
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub enum Shape {
+    Box { min: (f32, f32, f32), max: (f32, f32, f32) },
+    // polygon on x-y plane with the range of z.
+    Polygon { points: Vec<(f32, f32)>, z: (f32, f32) },
+    // named zone, resource reporting a place inside it belongs to the region.
+    Zone(Place),
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Region {
+    name: String,
+    #[serde(default)]
+    priority: i32,
+    shape: Shape,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ServiceArea {
+    regions: Vec<Region>,
+}
+
+impl Region {
+    pub fn new(name: String, priority: i32, shape: Shape) -> Self {
+        Self { name, priority, shape }
+    }
+
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn contains(&self, p: &Position, place: Option<&Place>) -> bool {
+        match &self.shape {
+            Shape::Box { min, max } => {
+                p.x > min.0 && p.x < max.0
+                && p.y > min.1 && p.y < max.1
+                && p.z > min.2 && p.z < max.2
+            },
+            Shape::Polygon { points, z } => {
+                p.z > z.0 && p.z < z.1 && in_polygon(points, p.x, p.y)
+            },
+            Shape::Zone(zone) => place.is_some_and(|pl| pl.is_in(zone)),
+        }
+    }
+
+    // zone has no size, and it is treated as the most specific region.
+    fn size(&self) -> f32 {
+        match &self.shape {
+            Shape::Box { min, max } => (max.0 - min.0) * (max.1 - min.1) * (max.2 - min.2),
+            Shape::Polygon { points, z } => polygon_area(points) * (z.1 - z.0),
+            Shape::Zone(_) => 0.0,
+        }
+    }
+
+    pub fn is_more_suitable(&self, other: &Region) -> bool {
+        self.suit_cmp(other) == Ordering::Greater
+    }
+
+    // compare how suitable the region is, greater is better.
+    fn suit_cmp(&self, other: &Region) -> Ordering {
+        self.priority.cmp(&other.priority)
+            .then(other.size().partial_cmp(&self.size()).unwrap_or(Ordering::Equal))
+    }
+}
+
+impl ServiceArea {
+    pub fn new(regions: Vec<Region>) -> Self {
+        Self { regions }
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> BoxResult<Self> {
+        let data = fs::read_to_string(path)?;
+        let regions: Vec<Region> = serde_json::from_str(&data)?;
+        Ok(Self { regions })
+    }
+
+    pub fn get_regions(&self) -> &Vec<Region> {
+        &self.regions
+    }
+
+    pub fn contains(&self, p: &Position, place: Option<&Place>) -> bool {
+        self.regions.iter().any(|r| r.contains(p, place))
+    }
+
+    pub fn best_region(&self, p: &Position, place: Option<&Place>) -> Option<&Region> {
+        best_region(&self.regions, p, place)
+    }
+}
+
+// used to be the hard-coded bounds of every TAPE.
+impl Default for ServiceArea {
+    fn default() -> Self {
+        Self {
+            regions: vec![Region::new(
+                "default".to_string(),
+                0,
+                Shape::Box { min: (-100.0, -100.0, -100.0), max: (100.0, 100.0, 100.0) },
+            )],
+        }
+    }
+}
+
+// the most suitable region which contains the position.
+pub fn best_region<'a>(regions: &'a [Region], p: &Position, place: Option<&Place>) -> Option<&'a Region> {
+    regions.iter()
+        .filter(|r| r.contains(p, place))
+        .max_by(|a, b| a.suit_cmp(b))
+}
+
+// ray casting.
+fn in_polygon(points: &[(f32, f32)], x: f32, y: f32) -> bool {
+    let mut inside = false;
+    let mut j = points.len().wrapping_sub(1);
+    for i in 0..points.len() {
+        let (xi, yi) = points[i];
+        let (xj, yj) = points[j];
+        if (yi > y) != (yj > y) && x < (xj - xi) * (y - yi) / (yj - yi) + xi {
+            inside = !inside;
+        }
+        j = i;
+    }
+    inside
+}
+
+// shoelace formula.
+fn polygon_area(points: &[(f32, f32)]) -> f32 {
+    let mut sum = 0.0;
+    for i in 0..points.len() {
+        let (x1, y1) = points[i];
+        let (x2, y2) = points[(i + 1) % points.len()];
+        sum += x1 * y2 - x2 * y1;
+    }
+    (sum / 2.0).abs()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // L shape, the square 5..10 x 5..10 is cut out.
+    const L: [(f32, f32); 6] = [(0.0, 0.0), (10.0, 0.0), (10.0, 5.0), (5.0, 5.0), (5.0, 10.0), (0.0, 10.0)];
+    const TRIANGLE: [(f32, f32); 3] = [(0.0, 0.0), (10.0, 0.0), (5.0, 8.0)];
+
+    fn place(building: &str, floor: &str, room: Option<&str>) -> Place {
+        Place::new(None, Some(building.to_string()), Some(floor.to_string()), room.map(|r| r.to_string()))
+    }
+
+    #[test]
+    fn point_in_polygon() {
+        type Case<'a> = (&'a [(f32, f32)], (f32, f32), bool);
+        let cases: [Case; 12] = [
+            (&TRIANGLE, (5.0, 4.0), true),
+            (&TRIANGLE, (1.0, 0.5), true),
+            (&TRIANGLE, (1.0, 7.0), false),
+            (&TRIANGLE, (5.0, 8.5), false),
+            (&TRIANGLE, (-1.0, 0.5), false),
+            (&L, (2.0, 8.0), true),
+            (&L, (8.0, 2.0), true),
+            // in the notch of the concave polygon.
+            (&L, (8.0, 8.0), false),
+            (&L, (11.0, 2.0), false),
+            (&L, (2.0, -1.0), false),
+            // ray passes through the vertex (5, 5).
+            (&L, (1.0, 5.0), true),
+            (&[], (0.0, 0.0), false),
+        ];
+        for (points, (x, y), want) in cases {
+            assert_eq!(in_polygon(points, x, y), want, "({}, {}) in {:?}", x, y, points);
+        }
+    }
+
+    #[test]
+    fn polygon_area_of_shapes() {
+        assert_eq!(polygon_area(&TRIANGLE), 40.0);
+        assert_eq!(polygon_area(&L), 75.0);
+        // the order of points does not matter.
+        let reversed = L.iter().rev().copied().collect::<Vec<_>>();
+        assert_eq!(polygon_area(&reversed), 75.0);
+    }
+
+    #[test]
+    fn region_contains() {
+        let lab = Region::new("lab".to_string(), 0, Shape::Polygon { points: L.to_vec(), z: (0.0, 3.0) });
+        let hall = Region::new("hall".to_string(), 0, Shape::Box { min: (-1.0, -1.0, -1.0), max: (1.0, 1.0, 1.0) });
+        let floor = Region::new("3F".to_string(), 0, Shape::Zone(place("A", "3", None)));
+        let cases = [
+            (&lab, Position::new(2.0, 8.0, 1.0), None, true),
+            (&lab, Position::new(2.0, 8.0, 4.0), None, false),
+            (&lab, Position::new(8.0, 8.0, 1.0), None, false),
+            (&hall, Position::new(0.0, 0.5, -0.5), None, true),
+            (&hall, Position::new(0.0, 1.5, 0.0), None, false),
+            (&floor, Position::default(), Some(place("a", "3", Some("302"))), true),
+            (&floor, Position::default(), Some(place("A", "4", Some("402"))), false),
+            (&floor, Position::default(), None, false),
+        ];
+        for (region, p, pl, want) in cases {
+            assert_eq!(region.contains(&p, pl.as_ref()), want, "{} {:?} {:?}", region.get_name(), p, pl);
+        }
+    }
+
+    #[test]
+    fn best_region_by_priority_then_size() {
+        let regions = vec![
+            Region::new("hall".to_string(), 0, Shape::Box { min: (-100.0, -100.0, -100.0), max: (100.0, 100.0, 100.0) }),
+            Region::new("lab".to_string(), 0, Shape::Polygon { points: L.to_vec(), z: (0.0, 3.0) }),
+            Region::new("corridor".to_string(), 1, Shape::Box { min: (-1.0, 0.0, 0.0), max: (3.0, 100.0, 3.0) }),
+        ];
+        let best = |x, y| best_region(&regions, &Position::new(x, y, 1.0), None).map(|r| r.get_name());
+        assert_eq!(best(8.0, 2.0), Some("lab"));
+        assert_eq!(best(2.0, 8.0), Some("corridor"));
+        assert_eq!(best(50.0, 50.0), Some("hall"));
+        assert_eq!(best(500.0, 50.0), None);
+    }
+}
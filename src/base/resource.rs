@@ -4,8 +4,17 @@
 // information.
 
 use bluer::Address;
+use crate::base::{capability::Capability, region::{best_region, Region}};
 use serde::{Deserialize, Serialize};
-use std::{net::SocketAddr, path::PathBuf, time::Duration};
+use serde_json::{Map, Value};
+use lazy_static::lazy_static;
+use regex::Regex;
+use std::{fmt, net::{IpAddr, SocketAddr}, path::PathBuf, time::Duration};
+
+lazy_static! {
+    // name of place should start with digit or capital letter, so that "clean the room now" is not a place.
+    static ref PLACE_RE: Regex = Regex::new(r"(?i:\b(site|building|floor|room))\s+([A-Z0-9][\w-]*)").unwrap();
+}
 
 // resource is a physical or virtual device(including human agent and software), 
 // which can be used to execute intents. However, it may not be able to 
@@ -59,11 +68,14 @@ pub trait Resource: Send + Sync {
     fn get_status(&mut self) -> &mut Status;
     fn get_address(&self) -> ResourceAddress;
     fn get_interpreter(&self) -> &Interpreter;
+    // empty capability means resource only has free-text description.
+    fn get_capability(&self) -> &Capability;
     fn display_status(&self) -> String;
 
     fn set_status(&mut self, status: Status);
     fn set_interpreter(&mut self, interpreter: Interpreter);
     fn set_description(&mut self, description: String);
+    fn set_capability(&mut self, capability: Capability);
 
     fn is_interpreter_none(&self) -> bool;
 }
@@ -73,6 +85,8 @@ pub enum ResourceAddress {
     Bluetooth(Address),
     Wifi(String),
     Internet(SocketAddr),
+    Mqtt(String),
+    Coap(SocketAddr),
 }
 
 #[derive(Serialize, Deserialize)]
@@ -84,19 +98,33 @@ pub enum Interpreter {
     None,
 }
 
+impl Interpreter {
+    // script interpreter also translate action calls of declared capability.
+    pub fn is_script(&self) -> bool {
+        matches!(self, Interpreter::PathBuf(_))
+    }
+}
+
 // Status is unique for each resource. However, there are some common statuses.
+// statuses unique to the resource are kept in fields, which are declared by its capability.
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Status {
     // aviliability shows the resource is available or not.
     aviliability: bool,
     // position shows the resource's position.
     position: Position,
+    // place shows where the resource is in discrete way, None means the resource do not care about place.
+    #[serde(default)]
+    place: Option<Place>,
     // dealing means resource now dealing inten number.
     dealing: u64,
     total_busy: Duration,
     // busy_time shows how much time the resource need to execute next intent.
     busy_time: Duration, 
     average_time: Duration, // every average time is calculate by 0.8x(average_time) + 0.2x(busy_time/dealing)  
+    // custom fields like temperature of water heater, or stock of fridge.
+    #[serde(default, skip_serializing_if = "Map::is_empty")]
+    fields: Map<String, Value>,
 }
 
 impl Status {
@@ -104,13 +132,32 @@ impl Status {
         Self { 
             aviliability, 
             position: Position::new(position.0, position.1, position.2), 
+            place: None,
             dealing: 0, 
             total_busy: Duration::from_secs(0), 
             busy_time,
             average_time: Duration::from_secs(10),
+            fields: Map::new(),
         }
     }
 
+    pub fn get_field(&self, name: &str) -> Option<&Value> {
+        self.fields.get(name)
+    }
+
+    pub fn get_fields(&self) -> &Map<String, Value> {
+        &self.fields
+    }
+
+    pub fn set_field(&mut self, name: String, value: Value) {
+        self.fields.insert(name, value);
+    }
+
+    // keep only fields which pass the check.
+    pub fn retain_fields<F: FnMut(&String, &mut Value) -> bool>(&mut self, f: F) {
+        self.fields.retain(f);
+    }
+
     pub fn get_aviliability(&self) -> bool {
         self.aviliability
     }
@@ -119,6 +166,14 @@ impl Status {
         &self.position
     }
 
+    pub fn get_place(&self) -> Option<&Place> {
+        self.place.as_ref()
+    }
+
+    pub fn set_place(&mut self, place: Option<Place>) {
+        self.place = place;
+    }
+
     pub fn get_dealing(&self) -> u64 {
         self.dealing
     }
@@ -148,7 +203,7 @@ impl Status {
         self.average_time = self.average_time.mul_f32(0.9) + exec_time.mul_f32(0.1);
     }
 
-    pub fn get_busy_time(&mut self) -> Duration {
+    pub fn get_busy_time(&self) -> Duration {
         self.busy_time
     }
 
@@ -175,13 +230,12 @@ impl Status {
 
 // position is a common field for all resources.
 // it is a 3D vector, which can be used to describe the position of the resource.
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
 pub struct Position {
     pub x: f32,
     pub y: f32,
     pub z: f32,
-} // TODO: make three dimension more flexible,
-    // so that it can be used to describe discrete position like floor and room.
+}
 
 impl Position {
     pub fn new(x: f32, y: f32, z: f32) -> Self {
@@ -193,17 +247,87 @@ impl Position {
     }
 }
 
+// place describe discrete position in hierarchy: site > building > floor > room.
+// level which is None means unknown, or the whole of upper level when used as an area.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq)]
+pub struct Place {
+    pub site: Option<String>,
+    pub building: Option<String>,
+    pub floor: Option<String>,
+    pub room: Option<String>,
+}
+
+impl Place {
+    pub fn new(site: Option<String>, building: Option<String>, floor: Option<String>, room: Option<String>) -> Self {
+        Self { site, building, floor, room }
+    }
+
+    // find place referenced by intent, like "turn off lights in room 302" or "floor 3 of building A".
+    pub fn from_description(description: &str) -> Option<Place> {
+        let mut place = Place::default();
+        for c in PLACE_RE.captures_iter(description) {
+            let value = Some(c[2].to_string());
+            match c[1].to_lowercase().as_str() {
+                "site" => place.site = value,
+                "building" => place.building = value,
+                "floor" => place.floor = value,
+                _ => place.room = value,
+            }
+        }
+        if place == Place::default() {
+            return None;
+        }
+        Some(place)
+    }
+
+    // whether the place is inside the area, every known level of area should be the same.
+    pub fn is_in(&self, area: &Place) -> bool {
+        fn level_in(p: &Option<String>, a: &Option<String>) -> bool {
+            match a {
+                Some(a) => p.as_ref().is_some_and(|p| p.eq_ignore_ascii_case(a)),
+                None => true,
+            }
+        }
+        level_in(&self.site, &area.site)
+        && level_in(&self.building, &area.building)
+        && level_in(&self.floor, &area.floor)
+        && level_in(&self.room, &area.room)
+    }
+}
+
+impl fmt::Display for Place {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let levels = [("site", &self.site), ("building", &self.building), ("floor", &self.floor), ("room", &self.room)];
+        let known = levels.iter()
+            .filter_map(|(l, v)| v.as_ref().map(|v| format!("{l} {v}")))
+            .collect::<Vec<String>>();
+        write!(f, "{}", known.join(" > "))
+    }
+}
+
+// resource which do not report place is not limited by area.
+pub fn is_in_area(place: Option<&Place>, area: Option<&Place>) -> bool {
+    match (place, area) {
+        (Some(p), Some(a)) => p.is_in(a),
+        _ => true,
+    }
+}
+
 // we do not need such function, instead we will use hashmap.
 // pub fn find_resource<'a>(device_name: String)
 
+// TAPE register with its service regions, resource ask for TAPE with its position and place.
 #[derive(Serialize, Deserialize)]
 pub struct RegisterServer {
     tape: bool,
     iaddr: Option<SocketAddr>,
     oaddr: Option<SocketAddr>,
-    x_dis: (f32, f32),
-    y_dis: (f32, f32),
-    z_dis: (f32, f32),
+    #[serde(default)]
+    regions: Vec<Region>,
+    #[serde(default)]
+    position: Position,
+    #[serde(default)]
+    place: Option<Place>,
 }
 
 impl RegisterServer {
@@ -211,15 +335,17 @@ impl RegisterServer {
         tape: bool, 
         iaddr: Option<SocketAddr>, 
         oaddr: Option<SocketAddr>, 
-        dis: ((f32, f32), (f32, f32), (f32, f32)), 
+        regions: Vec<Region>,
+        position: Position,
+        place: Option<Place>,
     ) -> Self {
         Self {
             tape,
             iaddr,
             oaddr,
-            x_dis: dis.0,
-            y_dis: dis.1,
-            z_dis: dis.2,
+            regions,
+            position,
+            place,
         }
     }
 
@@ -235,14 +361,17 @@ impl RegisterServer {
         self.oaddr.unwrap().clone()
     }
 
-    pub fn is_position_suit(&self, p: &RegisterServer) -> bool {
-        let v_position = ((-100.0, 100.0), (-100.0, 100.0), (-100.0, 100.0));
+    // unspecified ip of addresses is replaced by the given one.
+    pub fn fill_ip(&mut self, ip: IpAddr) {
+        for addr in [&mut self.iaddr, &mut self.oaddr].into_iter().flatten() {
+            if addr.ip().is_unspecified() {
+                addr.set_ip(ip);
+            }
+        }
+    }
 
-            p.x_dis.0 > (v_position.0).0 
-        &&  p.x_dis.1 < (v_position.0).1
-        &&  p.y_dis.0 > (v_position.1).0
-        &&  p.y_dis.1 < (v_position.1).1
-        &&  p.z_dis.0 > (v_position.2).0
-        &&  p.z_dis.1 < (v_position.2).1
+    // the most suitable region of this TAPE for the requester, None if it is out of service.
+    pub fn suit_region(&self, p: &RegisterServer) -> Option<&Region> {
+        best_region(&self.regions, &p.position, p.place.as_ref())
     }
-}
\ No newline at end of file
+}
@@ -1,48 +1,126 @@
 // in this file, we will store rules for judging the intent.
+// a rule may have a time window, it only judges intents inside the window, see calendar.
+// user rules are kept in RULE_FILE, so that they survive restart, and they are saved whenever
+// they change. expired rules are dropped by `keep_rules` every EXPIRE_INTERVAL.
+// a rule denies intents it matches by default, or allows them. user rules are judged in order
+// of higher priority first, then the older first, and combined by the strategy of the rule set:
+// FirstMatch     the first matched rule decides, so a specific allow can override a broader deny.
+// DenyOverrides  any matched deny rule rejects the intent, allow rules make no difference.
+// intents matched by no rule are accepted. static rules always deny and are judged in order of id.
+// RULE_FILE which can not be read is moved to "rules.json.bad", and rules are saved to RULE_FILE again.
+// a rule of RULE_FILE which can not be read is skipped, except a Program rule whose module can not be
+// loaded, it is kept unloaded and denies as it would if its module failed, see wasmrule.
 
 use std::{
     collections::HashMap, 
-    path::PathBuf, 
+    fs,
+    path::{Path, PathBuf}, 
     sync::{Arc, LazyLock}, 
-    time::{Duration, Instant},
+    time::Duration,
 };
-use chrono::Weekday;
-use tokio::sync::Mutex;
+use chrono::{DateTime, FixedOffset, Local, TimeDelta, Utc, Weekday};
+use log::{error, info, warn};
+use tokio::{sync::Mutex, time::sleep};
 use lazy_static::lazy_static;
-use serde::{Deserialize, Serialize};
+use serde::{de, Deserialize, Deserializer, Serialize};
+use serde_json::Value;
 use crate::{
-    tools::idgen::{generate_id, IdType},
+    tools::{idgen::{generate_id, IdType}, wasmrule::WasmRule},
     base::intent::IntentSource,
     base::intent::Intent,
     base::staticrule,
+    base::ruleexpr::RuleExpr,
+    base::calendar::{Holidays, TimeWindow},
+    base::errort::BoxResult,
 };
 
+pub const RULE_FILE: &str = "rules.json";
+const EXPIRE_INTERVAL: Duration = Duration::from_secs(60);
+
 lazy_static! {
-    pub static ref RULES: Arc<Mutex<RuleSet>> = Arc::new(Mutex::new(RuleSet::new()));
+    pub static ref RULES: Arc<Mutex<RuleSet>> = Arc::new(Mutex::new(RuleSet::load_or_new(RULE_FILE)));
 }
 
 // judge whether to accept the intent.
-// actually rule means not to do something.
+// actually rule means not to do something, unless its effect is Allow.
 pub enum RuleDetail {
     Function(fn(&mut Intent) -> bool),
     AsyncF(String),
-    Program(PathBuf),
+    Program(WasmRule), // based on the decision of a WebAssembly module, see wasmrule.
     Prompt(String),
     Source(IntentSource), // based on the source of the intent.
-    Time, // based on the time to reject the intent.
+    Time, // reject every intent while the rule is active, use with window.
     Weekday(Weekday), // based on the weekday to reject the intent.
+    StatusField(FieldCondition), // based on the custom status field of a resource.
+    Expr(RuleExpr), // based on the compiled rule expression, see ruleexpr.
     Undefine,
 }
 
-#[derive(Deserialize, Serialize)]
+#[derive(Deserialize, Serialize, Clone)]
 pub enum TransRuleDetail {
     Prompt(String),
     Source(IntentSource), // based on the source of the intent.
-    Time, // based on the time to reject the intent.
+    Time, // reject every intent while the rule is active, use with window.
     Weekday(TransWeekday), // based on the weekday to reject the intent.
+    StatusField(FieldCondition), // based on the custom status field of a resource.
+    Expr(RuleExpr), // like {"Expr": "source == Input and time in 22:00..06:00"}.
+    Program(WasmRule), // like {"Program": "night.wasm"}, name of module in MODULE_DIR.
 }
 
-#[derive(Deserialize, Serialize)]
+// condition on custom status field, like {"resource": "Instant water heater", "field": "temperature", "op": "Gt", "value": 70}.
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct FieldCondition {
+    pub resource: String,
+    pub field: String,
+    pub op: Compare,
+    pub value: Value,
+}
+
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Compare {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl FieldCondition {
+    // numbers and strings are ordered, other values can only be equal or not.
+    pub fn holds(&self, v: &Value) -> bool {
+        let ordering = match (v, &self.value) {
+            (Value::Number(a), Value::Number(b)) => a.as_f64().zip(b.as_f64()).and_then(|(a, b)| a.partial_cmp(&b)),
+            (Value::String(a), Value::String(b)) => Some(a.cmp(b)),
+            _ => None,
+        };
+        match (self.op, ordering) {
+            (Compare::Eq, _) => *v == self.value,
+            (Compare::Ne, _) => *v != self.value,
+            (Compare::Lt, Some(o)) => o.is_lt(),
+            (Compare::Le, Some(o)) => o.is_le(),
+            (Compare::Gt, Some(o)) => o.is_gt(),
+            (Compare::Ge, Some(o)) => o.is_ge(),
+            _ => false,
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Effect {
+    #[default]
+    Deny,
+    Allow,
+}
+
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Strategy {
+    #[default]
+    FirstMatch,
+    DenyOverrides,
+}
+
+#[derive(Deserialize, Serialize, Clone, Copy)]
 pub enum TransWeekday {
     /// Monday.
     Mon = 0,
@@ -67,7 +145,14 @@ pub struct Rule {
     // we encourage that one Rule judge one feature of the intent.
     detail: RuleDetail,
     valid_time: Duration,
-    created_time: Instant,
+    created_time: DateTime<Utc>,
+    // None means the rule is always active.
+    window: Option<TimeWindow>,
+    // user or resource who add the rule.
+    author: Option<String>,
+    effect: Effect,
+    // rule with higher priority is judged first.
+    priority: i32,
 }
 
 #[derive(Deserialize, Serialize)]
@@ -76,6 +161,107 @@ pub struct TransRule {
     pub description: String,
     pub valid_time: Duration,
     pub detail: TransRuleDetail,
+    #[serde(default)]
+    pub window: Option<TimeWindow>,
+    #[serde(default)]
+    pub effect: Effect,
+    #[serde(default)]
+    pub priority: i32,
+}
+
+// rules as they are kept in RULE_FILE.
+#[derive(Deserialize, Serialize, Default)]
+struct StoredRuleSet {
+    #[serde(default)]
+    strategy: Strategy,
+    #[serde(default)]
+    rules: Vec<Value>,
+}
+
+#[derive(Deserialize, Serialize)]
+struct StoredRule {
+    id: i64,
+    name: String,
+    description: String,
+    valid_time: Duration,
+    created_time: DateTime<Utc>,
+    #[serde(default)]
+    author: Option<String>,
+    #[serde(default)]
+    window: Option<TimeWindow>,
+    #[serde(default)]
+    effect: Effect,
+    #[serde(default)]
+    priority: i32,
+    #[serde(deserialize_with = "stored_detail")]
+    detail: TransRuleDetail,
+}
+
+// module of a stored Program rule may be gone since it was saved, the rule is kept anyway.
+fn stored_detail<'de, D: Deserializer<'de>>(deserializer: D) -> Result<TransRuleDetail, D::Error> {
+    let v = Value::deserialize(deserializer)?;
+    if let Some(name) = v.get("Program").and_then(Value::as_str) {
+        return Ok(TransRuleDetail::Program(WasmRule::load_or_unloaded(name)));
+    }
+    serde_json::from_value(v).map_err(de::Error::custom)
+}
+
+impl TransRule {
+    pub fn into_rule(self, author: Option<String>) -> Rule {
+        let mut r = Rule::new(self.name, self.description, self.detail.into_detail(), self.valid_time);
+        r.set_window(self.window);
+        r.set_author(author);
+        r.set_effect(self.effect);
+        r.set_priority(self.priority);
+        r
+    }
+}
+
+impl TransRuleDetail {
+    pub fn into_detail(self) -> RuleDetail {
+        match self {
+            TransRuleDetail::Time => RuleDetail::Time,
+            TransRuleDetail::Prompt(s) => RuleDetail::Prompt(s),
+            TransRuleDetail::Source(s) => RuleDetail::Source(s),
+            TransRuleDetail::StatusField(c) => RuleDetail::StatusField(c),
+            TransRuleDetail::Expr(e) => RuleDetail::Expr(e),
+            TransRuleDetail::Program(w) => RuleDetail::Program(w),
+            TransRuleDetail::Weekday(w) => RuleDetail::Weekday(match w {
+                TransWeekday::Mon => Weekday::Mon,
+                TransWeekday::Tue => Weekday::Tue,
+                TransWeekday::Wed => Weekday::Wed,
+                TransWeekday::Thu => Weekday::Thu,
+                TransWeekday::Fri => Weekday::Fri,
+                TransWeekday::Sat => Weekday::Sat,
+                TransWeekday::Sun => Weekday::Sun,
+            }),
+        }
+    }
+}
+
+impl RuleDetail {
+    // None for details which can only be given by code.
+    pub fn to_trans(&self) -> Option<TransRuleDetail> {
+        let detail = match self {
+            RuleDetail::Time => TransRuleDetail::Time,
+            RuleDetail::Prompt(s) => TransRuleDetail::Prompt(s.clone()),
+            RuleDetail::Source(s) => TransRuleDetail::Source(s.clone()),
+            RuleDetail::StatusField(c) => TransRuleDetail::StatusField(c.clone()),
+            RuleDetail::Expr(e) => TransRuleDetail::Expr(e.clone()),
+            RuleDetail::Program(w) => TransRuleDetail::Program(w.clone()),
+            RuleDetail::Weekday(w) => TransRuleDetail::Weekday(match w {
+                Weekday::Mon => TransWeekday::Mon,
+                Weekday::Tue => TransWeekday::Tue,
+                Weekday::Wed => TransWeekday::Wed,
+                Weekday::Thu => TransWeekday::Thu,
+                Weekday::Fri => TransWeekday::Fri,
+                Weekday::Sat => TransWeekday::Sat,
+                Weekday::Sun => TransWeekday::Sun,
+            }),
+            _ => return None,
+        };
+        Some(detail)
+    }
 }
 
 impl Rule {
@@ -88,7 +274,7 @@ impl Rule {
                 break;
             }
         }
-        Self { id: new_id, name, description, detail, valid_time, created_time: Instant::now() }
+        Self { id: new_id, name, description, detail, valid_time, created_time: Utc::now(), window: None, author: None, effect: Effect::Deny, priority: 0 }
     }
 
     pub fn get_id(&self) -> i64 {
@@ -111,12 +297,92 @@ impl Rule {
         self.valid_time
     }
 
-    pub fn get_created_time(&self) -> Instant {
+    pub fn get_created_time(&self) -> DateTime<Utc> {
         self.created_time
     }
 
+    // None if the rule never expire in practice.
+    pub fn get_expire_time(&self) -> Option<DateTime<Utc>> {
+        TimeDelta::from_std(self.valid_time).ok().and_then(|d| self.created_time.checked_add_signed(d))
+    }
+
     pub fn is_expired(&self) -> bool {
-        self.created_time + self.valid_time <= Instant::now()
+        self.get_expire_time().is_some_and(|t| t <= Utc::now())
+    }
+
+    pub fn get_author(&self) -> Option<&str> {
+        self.author.as_deref()
+    }
+
+    pub fn set_author(&mut self, author: Option<String>) {
+        self.author = author;
+    }
+
+    pub fn get_effect(&self) -> Effect {
+        self.effect
+    }
+
+    pub fn set_effect(&mut self, effect: Effect) {
+        self.effect = effect;
+    }
+
+    pub fn get_priority(&self) -> i32 {
+        self.priority
+    }
+
+    pub fn set_priority(&mut self, priority: i32) {
+        self.priority = priority;
+    }
+
+    pub fn get_window(&self) -> Option<&TimeWindow> {
+        self.window.as_ref()
+    }
+
+    pub fn set_window(&mut self, window: Option<TimeWindow>) {
+        self.window = window;
+    }
+
+    // whether the rule judges intents now.
+    pub fn is_active(&self, now: DateTime<Utc>, holidays: &Holidays) -> bool {
+        self.window.as_ref().is_none_or(|w| w.is_active(now, holidays))
+    }
+
+    // the time which time and weekday of the rule are about, in the timezone of its window.
+    pub fn local_time(&self, now: DateTime<Utc>) -> DateTime<FixedOffset> {
+        match &self.window {
+            Some(w) => w.in_timezone(now),
+            None => now.with_timezone(&Local).fixed_offset(),
+        }
+    }
+
+    fn to_stored(&self) -> Option<StoredRule> {
+        Some(StoredRule {
+            id: self.id,
+            name: self.name.clone(),
+            description: self.description.clone(),
+            valid_time: self.valid_time,
+            created_time: self.created_time,
+            author: self.author.clone(),
+            window: self.window.clone(),
+            effect: self.effect,
+            priority: self.priority,
+            detail: self.detail.to_trans()?,
+        })
+    }
+
+    fn from_stored(r: StoredRule) -> Self {
+        Self {
+            id: r.id,
+            name: r.name,
+            description: r.description,
+            detail: r.detail.into_detail(),
+            valid_time: r.valid_time,
+            created_time: r.created_time,
+            window: r.window,
+            author: r.author,
+            effect: r.effect,
+            priority: r.priority,
+        }
     }
 
     // we don't provide the function to change the rule directly, 
@@ -129,17 +395,76 @@ impl Rule {
 
 pub struct RuleSet {
     rules: Vec<Rule>,
+    strategy: Strategy,
+    // file the rules are saved to, None means they are only kept in memory.
+    path: Option<PathBuf>,
 }
 
 impl RuleSet {
-    fn new() -> Self {
+    pub fn new(path: Option<PathBuf>) -> Self {
         Self {
-            rules: vec![]
+            rules: vec![],
+            strategy: Strategy::default(),
+            path,
+        }
+    }
+
+    // missing file means no rule is added yet, rules which can not be read are skipped.
+    pub fn load<P: AsRef<Path>>(path: P) -> BoxResult<Self> {
+        let path = path.as_ref();
+        let mut set = Self::new(Some(path.to_path_buf()));
+        if !path.exists() {
+            return Ok(set);
+        }
+        let stored: StoredRuleSet = serde_json::from_str(&fs::read_to_string(path)?)?;
+        set.strategy = stored.strategy;
+        for v in stored.rules {
+            match serde_json::from_value::<StoredRule>(v) {
+                Ok(r) => set.rules.push(Rule::from_stored(r)),
+                Err(e) => warn!("skip rule in {}: {}", path.display(), e),
+            }
+        }
+        info!("{} rules are loaded from {}", set.rules.len(), path.display());
+        Ok(set)
+    }
+
+    // broken file is kept aside, so that new rules never overwrite what the user may fix.
+    pub fn load_or_new<P: AsRef<Path>>(path: P) -> Self {
+        let path = path.as_ref();
+        Self::load(path).unwrap_or_else(|e| {
+            let bad = path.with_extension("json.bad");
+            error!("load rules from {} error: {}, it is moved to {}", path.display(), e, bad.display());
+            if let Err(e) = fs::rename(path, &bad) {
+                error!("move {} error: {}, rules are only kept in memory", path.display(), e);
+                return Self::new(None);
+            }
+            Self::new(Some(path.to_path_buf()))
+        })
+    }
+
+    // write to a temporary file first, so that a crash never leave half of the rules.
+    fn save(&self) -> BoxResult<()> {
+        let path = match &self.path {
+            Some(p) => p,
+            None => return Ok(()),
+        };
+        let rules = self.rules.iter().filter_map(|r| r.to_stored()).map(serde_json::to_value).collect::<Result<Vec<Value>, _>>()?;
+        let stored = StoredRuleSet { strategy: self.strategy, rules };
+        let tmp = path.with_extension("tmp");
+        fs::write(&tmp, serde_json::to_string_pretty(&stored)?)?;
+        fs::rename(&tmp, path)?;
+        Ok(())
+    }
+
+    fn persist(&self) {
+        if let Err(e) = self.save() {
+            warn!("save rules error: {}", e);
         }
     }
     
     pub fn add_rule(&mut self, rule: Rule) {
         self.rules.push(rule);
+        self.persist();
     }
     
     pub fn get_rules_by_name(&self, name: &str) -> Vec<&Rule> {
@@ -150,17 +475,80 @@ impl RuleSet {
         self.rules.iter().find(|r: &&Rule| r.id == id)
     }
     
-    pub fn delete_rule(&mut self, id: i64) {
+    // false if there is no such rule.
+    pub fn delete_rule(&mut self, id: i64) -> bool {
+        let count = self.rules.len();
         self.rules.retain(|r: &Rule| r.id != id);
+        if self.rules.len() == count {
+            return false;
+        }
+        self.persist();
+        true
+    }
+
+    // the new rule takes the id and the place of the old one, so that no intent is judged without either.
+    pub fn replace_rule(&mut self, id: i64, mut rule: Rule) -> bool {
+        let old = match self.rules.iter_mut().find(|r| r.id == id) {
+            Some(r) => r,
+            None => return false,
+        };
+        rule.id = id;
+        *old = rule;
+        self.persist();
+        true
     }
 
     pub fn expire_rules(&mut self) {
+        let count = self.rules.len();
         self.rules.retain(|r: &Rule| !r.is_expired() || r.id < 1000);
+        if self.rules.len() != count {
+            info!("{} rules are expired", count - self.rules.len());
+            self.persist();
+        }
+    }
+
+    // a copy only kept in memory, rules given by code are not copied.
+    pub fn snapshot(&self) -> RuleSet {
+        let mut set = RuleSet::new(None);
+        set.strategy = self.strategy;
+        set.rules = self.rules.iter().filter_map(|r| r.to_stored()).map(Rule::from_stored).collect();
+        set
     }
 
     pub fn iter_rules(&self) -> impl Iterator<Item = &Rule> {
         self.rules.iter()
     }
+
+    // rules in the order they are judged.
+    pub fn ordered_rules(&self) -> Vec<&Rule> {
+        let mut rules = self.rules.iter().collect::<Vec<&Rule>>();
+        rules.sort_by_key(|r| (std::cmp::Reverse(r.priority), r.created_time, r.id));
+        rules
+    }
+
+    pub fn get_strategy(&self) -> Strategy {
+        self.strategy
+    }
+
+    pub fn set_strategy(&mut self, strategy: Strategy) {
+        self.strategy = strategy;
+        self.persist();
+    }
+}
+
+// static rules in the order they are judged.
+pub fn ordered_static_rules() -> Vec<&'static Rule> {
+    let mut rules = STATIC_RULES.values().collect::<Vec<&Rule>>();
+    rules.sort_by_key(|r| r.id);
+    rules
+}
+
+// drop expired user rules every EXPIRE_INTERVAL in background, RULES loads them when it is first used.
+pub async fn keep_rules() {
+    loop {
+        RULES.lock().await.expire_rules();
+        sleep(EXPIRE_INTERVAL).await;
+    }
 }
 
 pub static STATIC_RULES: LazyLock<HashMap<&str, Rule>> = LazyLock::new(|| HashMap::from([
@@ -173,7 +561,11 @@ pub static STATIC_RULES: LazyLock<HashMap<&str, Rule>> = LazyLock::new(|| HashMa
             // detail: RuleDetail::AsyncF("risk".to_string()), 
             detail: RuleDetail::Prompt(staticrule::RISK_PROMPT.to_string()), 
             valid_time: Duration::from_secs(0),
-            created_time: Instant::now(),
+            created_time: Utc::now(),
+            window: None,
+            author: None,
+            effect: Effect::Deny,
+            priority: 0,
         },
     ),
     // (
@@ -195,7 +587,11 @@ pub static STATIC_RULES: LazyLock<HashMap<&str, Rule>> = LazyLock::new(|| HashMa
             description: "emergency".to_string(), 
             detail: RuleDetail::Function(staticrule::emergency), 
             valid_time: Duration::from_secs(0),
-            created_time: Instant::now(),
+            created_time: Utc::now(),
+            window: None,
+            author: None,
+            effect: Effect::Deny,
+            priority: 0,
         },
     ),
     (
@@ -206,7 +602,11 @@ pub static STATIC_RULES: LazyLock<HashMap<&str, Rule>> = LazyLock::new(|| HashMa
             description: "reject".to_string(), 
             detail: RuleDetail::Function(staticrule::reject), 
             valid_time: Duration::from_secs(0),
-            created_time: Instant::now(),
+            created_time: Utc::now(),
+            window: None,
+            author: None,
+            effect: Effect::Deny,
+            priority: 0,
         },
     ),
     (
@@ -217,7 +617,11 @@ pub static STATIC_RULES: LazyLock<HashMap<&str, Rule>> = LazyLock::new(|| HashMa
             description: "set new rule".to_string(), 
             detail: RuleDetail::AsyncF("rule".to_string()), 
             valid_time: Duration::from_secs(0),
-            created_time: Instant::now(),
+            created_time: Utc::now(),
+            window: None,
+            author: None,
+            effect: Effect::Deny,
+            priority: 0,
         },
     ),
     (
@@ -228,7 +632,11 @@ pub static STATIC_RULES: LazyLock<HashMap<&str, Rule>> = LazyLock::new(|| HashMa
             description: "refresh status".to_string(), 
             detail: RuleDetail::AsyncF("status".to_string()), 
             valid_time: Duration::from_secs(0),
-            created_time: Instant::now(),
+            created_time: Utc::now(),
+            window: None,
+            author: None,
+            effect: Effect::Deny,
+            priority: 0,
         },
     ),
     (
@@ -239,7 +647,62 @@ pub static STATIC_RULES: LazyLock<HashMap<&str, Rule>> = LazyLock::new(|| HashMa
             description: "send directly to resource".to_string(), 
             detail: RuleDetail::AsyncF("direct".to_string()), 
             valid_time: Duration::from_secs(0),
-            created_time: Instant::now(),
+            created_time: Utc::now(),
+            window: None,
+            author: None,
+            effect: Effect::Deny,
+            priority: 0,
         },
     ),
 ]));
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn broken_rule_file_is_moved_aside() {
+        let path = std::env::temp_dir().join(format!("tape-rules-{}.json", std::process::id()));
+        let bad = path.with_extension("json.bad");
+        fs::write(&path, "{\"rules\": [").unwrap();
+        let set = RuleSet::load_or_new(&path);
+        let moved = fs::read_to_string(&bad);
+        let _ = fs::remove_file(&bad);
+        assert_eq!(moved.unwrap(), "{\"rules\": [");
+        assert!(!path.exists());
+        assert_eq!(set.path.as_deref(), Some(path.as_path()));
+        assert!(set.rules.is_empty());
+    }
+
+    #[test]
+    fn program_rule_without_module_is_kept() {
+        let path = std::env::temp_dir().join(format!("tape-program-rules-{}.json", std::process::id()));
+        let stored = serde_json::json!({
+            "strategy": "FirstMatch",
+            "rules": [{
+                "id": 7, "name": "night", "description": "no intent at night",
+                "valid_time": {"secs": 3600, "nanos": 0}, "created_time": Utc::now(),
+                "effect": "Deny", "detail": {"Program": "tape-missing.wasm"}
+            }, {
+                "id": 8, "name": "broken", "description": "unknown detail",
+                "valid_time": {"secs": 3600, "nanos": 0}, "created_time": Utc::now(),
+                "detail": {"Unknown": 1}
+            }]
+        });
+        fs::write(&path, stored.to_string()).unwrap();
+        let mut set = RuleSet::load(&path).unwrap();
+        // any change saves the rule set again.
+        set.set_strategy(Strategy::DenyOverrides);
+        let saved: Value = serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        let _ = fs::remove_file(&path);
+
+        assert_eq!(set.rules.len(), 1);
+        assert_eq!(set.rules[0].get_effect(), Effect::Deny);
+        match set.rules[0].get_rule_detail() {
+            RuleDetail::Program(w) => assert!(!w.is_loaded()),
+            _ => panic!("rule 7 is not a Program rule"),
+        }
+        assert_eq!(saved["rules"][0]["id"], 7);
+        assert_eq!(saved["rules"][0]["detail"]["Program"], "tape-missing.wasm");
+    }
+}
@@ -0,0 +1,587 @@
+// in this file, we will define the expression language of user rules.
+// like other rules, an expression tells which intents are NOT accepted, for example:
+//     source == Input and time in 22:00..06:00 and not description ~ "(?i)alarm"
+//     target == "oven" and (weekday in [Sat, Sun] or status("oven", "temperature") >= 200)
+// atoms:
+//     source == Tape | Input | Resource | Subsystem   where the intent comes from.
+//     resource == "name"                              resource which sends the intent.
+//     target == "name"                                resource the intent is routed to.
+//     description ~ "regex"                           description of the intent matches, `!~` for not.
+//     time in HH:MM..HH:MM                            time of day, the range may cross midnight,
+//                                                     from == to means the whole day like calendar.
+//     weekday in [Mon, Tue] | weekday == Sun
+//     priority >= 5                                   priority of the intent.
+//     holiday                                         today is a holiday, see calendar.
+//     status("resource", "field") > 70                custom status field declared by capability.
+//     true | false
+// time, weekday and holiday are in the timezone of the rule window, the local one of TAPE if it has none.
+// atoms are combined by `not`, `and`, `or` and parentheses, `!`, `&&` and `||` also work.
+// an expression is compiled once when the rule is added, and evaluated without LLM.
+// an atom can be unknown, like target before the intent is routed or status of an absent
+// resource, the rule only rejects when the expression is true whatever the unknown atoms are.
+
+use std::{cmp::Ordering, collections::HashMap, fmt, str::FromStr};
+use chrono::{DateTime, Datelike, FixedOffset, Timelike, Weekday};
+use regex::Regex;
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::Value;
+
+use crate::base::{
+    intent::IntentSource,
+    rule::{Compare, FieldCondition},
+};
+
+// status field values the expression needs, keyed by (resource, field).
+pub type FieldValues = HashMap<(String, String), Value>;
+
+// a compiled expression, it is kept with its source so that it can be shown and stored.
+#[derive(Clone)]
+pub struct RuleExpr {
+    source: String,
+    expr: Expr,
+}
+
+#[derive(Clone)]
+enum Expr {
+    Const(bool),
+    Not(Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Source(IntentSource),
+    Resource(String),
+    Target(String),
+    Description(Regex),
+    // minutes of the day, from is included and to is not, the same from and to is the whole day.
+    Time(u32, u32),
+    Weekday(Vec<Weekday>),
+    Priority(Compare, i64),
+    Holiday,
+    Field(FieldCondition),
+}
+
+// what the expression is evaluated against.
+pub struct RuleContext<'a> {
+    pub source: &'a IntentSource,
+    pub resource: Option<&'a str>,
+    // None before the intent is routed.
+    pub target: Option<&'a str>,
+    pub description: &'a str,
+    pub priority: i32,
+    // in the timezone of the rule, see `Rule::local_time`.
+    pub now: DateTime<FixedOffset>,
+    pub holiday: bool,
+    pub fields: &'a FieldValues,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Ident(String),
+    Str(String),
+    Num(f64),
+    // minutes of the day.
+    Clock(u32),
+    Op(&'static str),
+}
+
+const OPS: [&str; 17] = ["..", "==", "!=", "<=", ">=", "!~", "&&", "||", "<", ">", "~", "!", "(", ")", "[", "]", ","];
+
+fn lex(s: &str) -> Result<Vec<Token>, String> {
+    let chars = s.chars().collect::<Vec<char>>();
+    let mut tokens = vec![];
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+        } else if c == '"' {
+            let mut text = String::new();
+            i += 1;
+            loop {
+                match chars.get(i) {
+                    Some('"') => break,
+                    Some('\\') if chars.get(i + 1).is_some() => {
+                        text.push(chars[i + 1]);
+                        i += 2;
+                    },
+                    Some(c) => {
+                        text.push(*c);
+                        i += 1;
+                    },
+                    None => return Err("unterminated string".to_string()),
+                }
+            }
+            i += 1;
+            tokens.push(Token::Str(text));
+        } else if c.is_ascii_digit() || (c == '-' && chars.get(i + 1).is_some_and(|c| c.is_ascii_digit())) {
+            let start = i;
+            i += 1;
+            // `..` of a range is not part of the number.
+            while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == ':' || (chars[i] == '.' && chars.get(i + 1) != Some(&'.'))) {
+                i += 1;
+            }
+            let text = chars[start..i].iter().collect::<String>();
+            tokens.push(match text.split_once(':') {
+                Some((h, m)) => Token::Clock(clock(h, m).ok_or(format!("bad time of day `{}`", text))?),
+                None => Token::Num(text.parse().map_err(|_| format!("bad number `{}`", text))?),
+            });
+        } else if c.is_alphabetic() || c == '_' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            tokens.push(Token::Ident(chars[start..i].iter().collect()));
+        } else {
+            let rest = chars[i..].iter().take(2).collect::<String>();
+            match OPS.into_iter().find(|o| rest.starts_with(o)) {
+                Some(o) => {
+                    tokens.push(Token::Op(o));
+                    i += o.len();
+                },
+                None => return Err(format!("unexpected `{}`", c)),
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+fn clock(h: &str, m: &str) -> Option<u32> {
+    let (h, m) = (h.parse::<u32>().ok()?, m.parse::<u32>().ok()?);
+    if h > 24 || m > 59 || (h == 24 && m != 0) {
+        return None;
+    }
+    Some(h * 60 + m)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Result<Token, String> {
+        let t = self.tokens.get(self.pos).cloned().ok_or("unexpected end of expression".to_string())?;
+        self.pos += 1;
+        Ok(t)
+    }
+
+    // consume the keyword or operator if it is the next token.
+    fn eat(&mut self, words: &[&str]) -> bool {
+        let hit = match self.peek() {
+            Some(Token::Ident(w)) => words.iter().any(|k| w.eq_ignore_ascii_case(k)),
+            Some(Token::Op(o)) => words.contains(o),
+            _ => false,
+        };
+        if hit {
+            self.pos += 1;
+        }
+        hit
+    }
+
+    fn expect(&mut self, op: &str) -> Result<(), String> {
+        if self.eat(&[op]) {
+            Ok(())
+        } else {
+            Err(format!("expect `{}` {}", op, self.at()))
+        }
+    }
+
+    fn at(&self) -> String {
+        match self.peek() {
+            Some(t) => format!("before {:?}", t),
+            None => "at the end".to_string(),
+        }
+    }
+
+    fn or(&mut self) -> Result<Expr, String> {
+        let mut e = self.and()?;
+        while self.eat(&["or", "||"]) {
+            e = Expr::Or(Box::new(e), Box::new(self.and()?));
+        }
+        Ok(e)
+    }
+
+    fn and(&mut self) -> Result<Expr, String> {
+        let mut e = self.unary()?;
+        while self.eat(&["and", "&&"]) {
+            e = Expr::And(Box::new(e), Box::new(self.unary()?));
+        }
+        Ok(e)
+    }
+
+    fn unary(&mut self) -> Result<Expr, String> {
+        if self.eat(&["not", "!"]) {
+            return Ok(Expr::Not(Box::new(self.unary()?)));
+        }
+        if self.eat(&["("]) {
+            let e = self.or()?;
+            self.expect(")")?;
+            return Ok(e);
+        }
+        self.atom()
+    }
+
+    // `==` or `!=`, true for `==`.
+    fn equality(&mut self) -> Result<bool, String> {
+        match self.next()? {
+            Token::Op("==") => Ok(true),
+            Token::Op("!=") => Ok(false),
+            t => Err(format!("expect `==` or `!=` before {:?}", t)),
+        }
+    }
+
+    fn compare(&mut self) -> Result<Compare, String> {
+        match self.next()? {
+            Token::Op("==") => Ok(Compare::Eq),
+            Token::Op("!=") => Ok(Compare::Ne),
+            Token::Op("<") => Ok(Compare::Lt),
+            Token::Op("<=") => Ok(Compare::Le),
+            Token::Op(">") => Ok(Compare::Gt),
+            Token::Op(">=") => Ok(Compare::Ge),
+            t => Err(format!("expect comparison before {:?}", t)),
+        }
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        match self.next()? {
+            Token::Str(s) => Ok(s),
+            t => Err(format!("expect string before {:?}", t)),
+        }
+    }
+
+    fn ident(&mut self) -> Result<String, String> {
+        match self.next()? {
+            Token::Ident(s) => Ok(s),
+            t => Err(format!("expect name before {:?}", t)),
+        }
+    }
+
+    fn weekday(&mut self) -> Result<Weekday, String> {
+        let w = self.ident()?;
+        Weekday::from_str(&w).map_err(|_| format!("no such weekday `{}`", w))
+    }
+
+    fn value(&mut self) -> Result<Value, String> {
+        match self.next()? {
+            Token::Str(s) => Ok(Value::String(s)),
+            Token::Num(n) => serde_json::Number::from_f64(n).map(Value::Number).ok_or(format!("bad number {}", n)),
+            Token::Ident(w) if w == "true" || w == "false" => Ok(Value::Bool(w == "true")),
+            t => Err(format!("expect value before {:?}", t)),
+        }
+    }
+
+    fn atom(&mut self) -> Result<Expr, String> {
+        let name = self.ident()?;
+        let negate = |eq: bool, e: Expr| if eq { e } else { Expr::Not(Box::new(e)) };
+        let e = match name.to_lowercase().as_str() {
+            "true" => Expr::Const(true),
+            "false" => Expr::Const(false),
+            "holiday" => Expr::Holiday,
+            "source" => {
+                let eq = self.equality()?;
+                let s = self.ident()?;
+                let source = serde_json::from_value::<IntentSource>(Value::String(s.clone()))
+                    .map_err(|_| format!("no such source `{}`", s))?;
+                negate(eq, Expr::Source(source))
+            },
+            "resource" => {
+                let eq = self.equality()?;
+                negate(eq, Expr::Resource(self.string()?))
+            },
+            "target" => {
+                let eq = self.equality()?;
+                negate(eq, Expr::Target(self.string()?))
+            },
+            "description" => {
+                let eq = match self.next()? {
+                    Token::Op("~") => true,
+                    Token::Op("!~") => false,
+                    t => return Err(format!("expect `~` or `!~` before {:?}", t)),
+                };
+                let pattern = self.string()?;
+                let re = Regex::new(&pattern).map_err(|e| format!("bad regex `{}`: {}", pattern, e))?;
+                negate(eq, Expr::Description(re))
+            },
+            "time" => {
+                self.expect("in")?;
+                let from = match self.next()? {
+                    Token::Clock(m) => m,
+                    t => return Err(format!("expect time of day like 22:00 before {:?}", t)),
+                };
+                self.expect("..")?;
+                let to = match self.next()? {
+                    Token::Clock(m) => m,
+                    t => return Err(format!("expect time of day like 06:00 before {:?}", t)),
+                };
+                Expr::Time(from, to)
+            },
+            "weekday" => {
+                if self.eat(&["in"]) {
+                    self.expect("[")?;
+                    let mut days = vec![self.weekday()?];
+                    while self.eat(&[","]) {
+                        days.push(self.weekday()?);
+                    }
+                    self.expect("]")?;
+                    Expr::Weekday(days)
+                } else {
+                    let eq = self.equality()?;
+                    negate(eq, Expr::Weekday(vec![self.weekday()?]))
+                }
+            },
+            "priority" => {
+                let op = self.compare()?;
+                match self.next()? {
+                    Token::Num(n) if n.fract() == 0.0 => Expr::Priority(op, n as i64),
+                    t => return Err(format!("expect integer priority before {:?}", t)),
+                }
+            },
+            "status" => {
+                self.expect("(")?;
+                let resource = self.string()?;
+                self.expect(",")?;
+                let field = self.string()?;
+                self.expect(")")?;
+                let op = self.compare()?;
+                Expr::Field(FieldCondition { resource, field, op, value: self.value()? })
+            },
+            _ => return Err(format!("unknown atom `{}`", name)),
+        };
+        Ok(e)
+    }
+}
+
+// three-valued logic, None means unknown.
+fn and(a: Option<bool>, b: Option<bool>) -> Option<bool> {
+    match (a, b) {
+        (Some(false), _) | (_, Some(false)) => Some(false),
+        (Some(true), Some(true)) => Some(true),
+        _ => None,
+    }
+}
+
+fn or(a: Option<bool>, b: Option<bool>) -> Option<bool> {
+    match (a, b) {
+        (Some(true), _) | (_, Some(true)) => Some(true),
+        (Some(false), Some(false)) => Some(false),
+        _ => None,
+    }
+}
+
+fn compare(op: Compare, o: Ordering) -> bool {
+    match op {
+        Compare::Eq => o.is_eq(),
+        Compare::Ne => o.is_ne(),
+        Compare::Lt => o.is_lt(),
+        Compare::Le => o.is_le(),
+        Compare::Gt => o.is_gt(),
+        Compare::Ge => o.is_ge(),
+    }
+}
+
+impl Expr {
+    fn eval(&self, ctx: &RuleContext) -> Option<bool> {
+        match self {
+            Expr::Const(b) => Some(*b),
+            Expr::Not(e) => e.eval(ctx).map(|b| !b),
+            Expr::And(a, b) => and(a.eval(ctx), b.eval(ctx)),
+            Expr::Or(a, b) => or(a.eval(ctx), b.eval(ctx)),
+            Expr::Source(s) => Some(ctx.source == s),
+            Expr::Resource(r) => Some(ctx.resource == Some(r.as_str())),
+            Expr::Target(t) => ctx.target.map(|c| c == t),
+            Expr::Description(re) => Some(re.is_match(ctx.description)),
+            Expr::Time(from, to) => {
+                let now = ctx.now.hour() * 60 + ctx.now.minute();
+                if from == to {
+                    Some(true)
+                } else if from < to {
+                    Some(*from <= now && now < *to)
+                } else {
+                    Some(*from <= now || now < *to)
+                }
+            },
+            Expr::Weekday(days) => Some(days.contains(&ctx.now.weekday())),
+            Expr::Priority(op, p) => Some(compare(*op, (ctx.priority as i64).cmp(p))),
+            Expr::Holiday => Some(ctx.holiday),
+            Expr::Field(c) => ctx.fields.get(&(c.resource.clone(), c.field.clone())).map(|v| c.holds(v)),
+        }
+    }
+
+    fn fields<'a>(&'a self, out: &mut Vec<(&'a str, &'a str)>) {
+        match self {
+            Expr::Not(e) => e.fields(out),
+            Expr::And(a, b) | Expr::Or(a, b) => {
+                a.fields(out);
+                b.fields(out);
+            },
+            Expr::Field(c) => out.push((&c.resource, &c.field)),
+            _ => (),
+        }
+    }
+
+    fn has_target(&self) -> bool {
+        match self {
+            Expr::Not(e) => e.has_target(),
+            Expr::And(a, b) | Expr::Or(a, b) => a.has_target() || b.has_target(),
+            Expr::Target(_) => true,
+            _ => false,
+        }
+    }
+}
+
+impl RuleExpr {
+    pub fn compile(source: &str) -> Result<Self, String> {
+        let mut parser = Parser { tokens: lex(source)?, pos: 0 };
+        let expr = parser.or()?;
+        if parser.peek().is_some() {
+            return Err(format!("unexpected token {}", parser.at()));
+        }
+        Ok(Self { source: source.to_string(), expr })
+    }
+
+    pub fn get_source(&self) -> &str {
+        &self.source
+    }
+
+    // status fields to look up before evaluating, as (resource, field).
+    pub fn fields(&self) -> Vec<(&str, &str)> {
+        let mut out = vec![];
+        self.expr.fields(&mut out);
+        out
+    }
+
+    // whether the expression is about the resource which the intent is routed to.
+    pub fn has_target(&self) -> bool {
+        self.expr.has_target()
+    }
+
+    // Some(true) means the intent should be rejected, None means it can not be told yet.
+    pub fn eval(&self, ctx: &RuleContext) -> Option<bool> {
+        self.expr.eval(ctx)
+    }
+}
+
+impl fmt::Display for RuleExpr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.source)
+    }
+}
+
+// expression is stored as its source and compiled when it is loaded.
+impl Serialize for RuleExpr {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.source)
+    }
+}
+
+impl<'de> Deserialize<'de> for RuleExpr {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let source = String::deserialize(deserializer)?;
+        RuleExpr::compile(&source).map_err(de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+    use serde_json::json;
+
+    use super::*;
+
+    // Saturday 2024-05-04 23:30 at the rule, an input intent before it is routed.
+    fn eval(source: &str, fields: &FieldValues) -> Option<bool> {
+        let ctx = RuleContext {
+            source: &IntentSource::Input,
+            resource: Some("phone"),
+            target: None,
+            description: "turn on the oven",
+            priority: 3,
+            now: FixedOffset::east_opt(2 * 3600).unwrap().with_ymd_and_hms(2024, 5, 4, 23, 30, 0).unwrap(),
+            holiday: false,
+            fields,
+        };
+        RuleExpr::compile(source).unwrap().eval(&ctx)
+    }
+
+    #[test]
+    fn precedence() {
+        let cases = [
+            ("true or false and false", true),
+            ("(true or false) and false", false),
+            ("false and false or true", true),
+            ("false and (false or true)", false),
+            ("not false and false", false),
+            ("not (false and false)", true),
+            ("! true || true", true),
+            ("not not true", true),
+        ];
+        for (source, want) in cases {
+            assert_eq!(eval(source, &FieldValues::new()), Some(want), "{}", source);
+        }
+    }
+
+    #[test]
+    fn atoms() {
+        let fields = FieldValues::from([(("oven".to_string(), "temperature".to_string()), json!(220))]);
+        let cases = [
+            ("source == Input", true),
+            ("source != Input", false),
+            ("resource == \"phone\"", true),
+            ("description ~ \"(?i)OVEN\"", true),
+            ("description !~ \"oven\"", false),
+            ("time in 22:00..06:00", true),
+            ("time in 06:00..22:00", false),
+            ("time in 08:00..08:00", true),
+            ("time in 23:30..23:31", true),
+            ("time in 23:00..23:30", false),
+            ("weekday in [Sat, Sun]", true),
+            ("weekday == Mon", false),
+            ("priority >= 3", true),
+            ("priority < 3", false),
+            ("holiday", false),
+            ("status(\"oven\", \"temperature\") >= 200", true),
+        ];
+        for (source, want) in cases {
+            assert_eq!(eval(source, &fields), Some(want), "{}", source);
+        }
+    }
+
+    #[test]
+    fn unknown_identifiers_do_not_compile() {
+        let cases = [
+            "colour == \"red\"",
+            "source == Nowhere",
+            "weekday in [Mon, Funday]",
+            "time in 25:00..06:00",
+            "priority >= high",
+            "true and",
+            "(true",
+            "true false",
+        ];
+        for source in cases {
+            assert!(RuleExpr::compile(source).is_err(), "{}", source);
+        }
+    }
+
+    #[test]
+    fn unknown_atoms_propagate() {
+        // target is unknown before routing, and so is the status of an absent resource.
+        let cases = [
+            ("target == \"oven\"", None),
+            ("not target == \"oven\"", None),
+            ("target == \"oven\" and false", Some(false)),
+            ("target == \"oven\" and true", None),
+            ("target == \"oven\" or true", Some(true)),
+            ("target == \"oven\" or false", None),
+            ("status(\"lamp\", \"level\") > 70 and source == Tape", Some(false)),
+            ("status(\"lamp\", \"level\") > 70 or source == Input", Some(true)),
+            ("not (status(\"lamp\", \"level\") > 70 and true)", None),
+        ];
+        for (source, want) in cases {
+            assert_eq!(eval(source, &FieldValues::new()), want, "{}", source);
+        }
+    }
+}
@@ -1,16 +1,17 @@
-use log::info;
-use chrono::Weekday;
+use log::{info, warn};
 
 use crate::{
     base::{
         intent::{Intent, IntentType},
-        resource::{Position, Status},
+        region::SERVICE_AREA,
+        resource::Status,
         errort::{BoxResult, JudgeError},
-        rule::{Rule, RuleDetail, TransRule, TransRuleDetail, RULES}
+        rule::{TransRule, RULES}
     },
     components::linkhub::{
-        internet::seek::SOCKET,
-        seeker::{fresh_resource_status, remove_resource_by_name, BLUETOOTH_RESOURCES, INTERNET_RESOURCES,}
+        internet::seek::handover,
+        registry::RESOURCES,
+        seeker::{fresh_resource_status, remove_resource_by_name}
     }
 };
 
@@ -32,35 +33,18 @@ pub fn emergency(intent: &mut Intent) -> bool {
 }
 
 pub async fn rule(intent: &Intent) -> bool {
-    match try_add2rule(intent.get_description()).await {
+    // the rule belongs to who sends it.
+    let author = intent.get_user().or(intent.get_resource()).cloned();
+    match try_add2rule(intent.get_description(), author).await {
         Ok(_) => true,
         Err(_) => false,
     }
 }
 
-async fn try_add2rule(i: &str) -> BoxResult<()> {
+async fn try_add2rule(i: &str, author: Option<String>) -> BoxResult<()> {
     // parse the rule
     let rule: TransRule = serde_json::from_str(i)?;
-    let r_detail = match rule.detail {
-        TransRuleDetail::Time => RuleDetail::Time,
-        TransRuleDetail::Prompt(s) => RuleDetail::Prompt(s),
-        TransRuleDetail::Source(s) => RuleDetail::Source(s),
-        TransRuleDetail::Weekday(w) => {
-            let weekday: Weekday = match w as u8 {
-                0 => Weekday::Mon,
-                1 => Weekday::Thu,
-                2 => Weekday::Wed,
-                3 => Weekday::Tue,
-                4 => Weekday::Fri,
-                5 => Weekday::Sat,
-                6 => Weekday::Sun,
-                _ => return Err(Box::new(JudgeError::new("no such week"))),
-            };
-            RuleDetail::Weekday(weekday)
-        },
-    };
-    let r = Rule::new(rule.name, rule.description, r_detail, rule.valid_time);
-    RULES.lock().await.add_rule(r);
+    RULES.lock().await.add_rule(rule.into_rule(author));
     Ok(())
 }
 
@@ -73,7 +57,12 @@ pub async fn status(intent: &Intent) -> bool {
 
 async fn try_fresh2status(i: &str, name: &str) -> BoxResult<()> {
     let status: Status = serde_json::from_str(i)?;
-    if !check_position(status.get_position()) {
+    if !SERVICE_AREA.lock().await.contains(status.get_position(), status.get_place()) {
+        match handover(name, &status).await {
+            Ok(true) => (),
+            Ok(false) => warn!("no TAPE can serve {} now", name),
+            Err(e) => warn!("handover {} error: {}", name, e),
+        }
         remove_resource_by_name(name).await;
         return Ok(());
     }
@@ -81,17 +70,6 @@ async fn try_fresh2status(i: &str, name: &str) -> BoxResult<()> {
     Ok(())
 }
 
-fn check_position(p: &Position) -> bool {
-    let v_position = ((-100.0, 100.0), (-100.0, 100.0), (-100.0, 100.0));
-
-    p.x > (v_position.0).0 
-    &&  p.x < (v_position.0).1
-    &&  p.y > (v_position.1).0
-    &&  p.y < (v_position.1).1
-    &&  p.z > (v_position.2).0
-    &&  p.z < (v_position.2).1
-}
-
 // direct command will have format:
 // trick:`resource_name`:`target_command`
 pub async fn direct(intent: &Intent) -> bool {
@@ -116,32 +94,13 @@ pub async fn direct(intent: &Intent) -> bool {
 }
 
 pub async fn directly_send(name: &str, command: &str) -> BoxResult<()> {
-
-    let r_m = INTERNET_RESOURCES.lock().await;
-    let r = r_m.get(name);
-    if r.is_some() {
-        let r = r.unwrap().lock().await;
-        let addr = r.get_address();
-        let data: Vec<u8> = command.as_bytes().to_vec();
-        SOCKET.lock().await.as_ref().unwrap().send_to(&data, addr).await?;
-        info!("message send");
-        return Ok(());
-    }
-    let _ = r;
-
-    let r_m = BLUETOOTH_RESOURCES.lock().await;
-    let r = r_m.get(name);
-    if r.is_some() {
-        let r = r.unwrap().lock().await;
-        let char = r.get_char().as_ref().unwrap();
-        let data: Vec<u8> = command.as_bytes().to_vec();
-        char.write(&data).await?;
-        info!("message send");
-        return Ok(());
-    }
-    let _ = r;
-    
-    Err(Box::new(JudgeError::new("")))
+    let r = match RESOURCES.lock().await.get(name) {
+        Some(r) => r,
+        None => return Err(Box::new(JudgeError::new(""))),
+    };
+    r.lock().await.send_raw(command).await?;
+    info!("message send");
+    Ok(())
 }
 
 // pub async fn risk(intent: &mut Intent) -> bool {
@@ -0,0 +1,238 @@
+// in this file, we will keep the status history of resources.
+// every status reported by a resource is kept as a sample, at most HISTORY_LEN samples for
+// each resource, so that load trends and when a resource went unavailable can be queried.
+// history of a removed resource ends with an unavailable sample and is dropped after KEEP_D.
+
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::Arc,
+    time::Duration,
+};
+use chrono::Local;
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use tokio::sync::Mutex;
+
+use crate::base::resource::{Position, Status};
+
+const HISTORY_LEN: usize = 360;
+const KEEP_D: Duration = Duration::from_secs(3600);
+// window of recent trends used by the router.
+pub const TREND_WINDOW: Duration = Duration::from_secs(120);
+
+lazy_static! {
+    pub static ref TELEMETRY: Arc<Mutex<TelemetryBook>> = Arc::new(Mutex::new(TelemetryBook::new()));
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Sample {
+    // unix time in milliseconds.
+    timestamp: i64,
+    aviliability: bool,
+    dealing: u64,
+    busy_time: Duration,
+    average_time: Duration,
+    position: Position,
+    // custom status fields of the resource.
+    #[serde(default, skip_serializing_if = "Map::is_empty")]
+    fields: Map<String, Value>,
+}
+
+// aggregate of samples in a window.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct Aggregate {
+    samples: usize,
+    // ratio of samples the resource is available.
+    availability: f32,
+    mean_dealing: f64,
+    max_dealing: u64,
+    mean_busy_time: Duration,
+    mean_average_time: Duration,
+    // distance the resource moved.
+    distance: f32,
+    // change of dealing per minute.
+    dealing_trend: f64,
+    // mean of numeric custom fields.
+    #[serde(default)]
+    field_means: HashMap<String, f64>,
+}
+
+pub struct TelemetryBook {
+    resources: HashMap<String, VecDeque<Sample>>,
+}
+
+impl Sample {
+    fn new(status: &Status) -> Self {
+        Self {
+            timestamp: Local::now().timestamp_millis(),
+            aviliability: status.get_aviliability(),
+            dealing: status.get_dealing(),
+            busy_time: status.get_busy_time(),
+            average_time: status.get_average_time(),
+            position: status.get_position().clone(),
+            fields: status.get_fields().clone(),
+        }
+    }
+
+    pub fn get_timestamp(&self) -> i64 {
+        self.timestamp
+    }
+
+    pub fn get_aviliability(&self) -> bool {
+        self.aviliability
+    }
+
+    pub fn get_dealing(&self) -> u64 {
+        self.dealing
+    }
+
+    pub fn get_position(&self) -> &Position {
+        &self.position
+    }
+
+    pub fn get_field(&self, name: &str) -> Option<&Value> {
+        self.fields.get(name)
+    }
+}
+
+impl Aggregate {
+    pub fn get_availability(&self) -> f32 {
+        self.availability
+    }
+
+    pub fn get_dealing_trend(&self) -> f64 {
+        self.dealing_trend
+    }
+
+    pub fn get_field_mean(&self, name: &str) -> Option<f64> {
+        self.field_means.get(name).copied()
+    }
+}
+
+fn since(window: Duration) -> i64 {
+    Local::now().timestamp_millis() - window.as_millis() as i64
+}
+
+fn distance(a: &Position, b: &Position) -> f32 {
+    ((a.x - b.x).powi(2) + (a.y - b.y).powi(2) + (a.z - b.z).powi(2)).sqrt()
+}
+
+// slope of dealing over time by least squares, in dealing per minute.
+fn trend(samples: &[&Sample]) -> f64 {
+    if samples.len() < 2 {
+        return 0.0;
+    }
+    let t0 = samples[0].timestamp;
+    let points = samples.iter().map(|s| ((s.timestamp - t0) as f64 / 60000.0, s.dealing as f64)).collect::<Vec<(f64, f64)>>();
+    let n = points.len() as f64;
+    let mean_t = points.iter().map(|p| p.0).sum::<f64>() / n;
+    let mean_d = points.iter().map(|p| p.1).sum::<f64>() / n;
+    let var = points.iter().map(|p| (p.0 - mean_t).powi(2)).sum::<f64>();
+    if var == 0.0 {
+        return 0.0;
+    }
+    points.iter().map(|p| (p.0 - mean_t) * (p.1 - mean_d)).sum::<f64>() / var
+}
+
+// samples which do not report the field, or report it in non-numeric, are not counted.
+fn field_means(samples: &[&Sample]) -> HashMap<String, f64> {
+    let mut sums: HashMap<String, (f64, usize)> = HashMap::new();
+    for s in samples.iter() {
+        for (k, v) in s.fields.iter() {
+            if let Some(f) = v.as_f64() {
+                let e = sums.entry(k.clone()).or_default();
+                e.0 += f;
+                e.1 += 1;
+            }
+        }
+    }
+    sums.into_iter().map(|(k, (sum, n))| (k, sum / n as f64)).collect()
+}
+
+impl TelemetryBook {
+    fn new() -> Self {
+        Self { resources: HashMap::new() }
+    }
+
+    pub fn record(&mut self, name: &str, status: &Status) {
+        let history = self.resources.entry(name.to_string()).or_default();
+        history.push_back(Sample::new(status));
+        if history.len() > HISTORY_LEN {
+            history.pop_front();
+        }
+        let old = since(KEEP_D);
+        self.resources.retain(|_, h| h.back().is_some_and(|s| s.timestamp > old));
+    }
+
+    // the resource is gone, its history is kept to tell when.
+    pub fn mark_removed(&mut self, name: &str) {
+        let history = match self.resources.get_mut(name) {
+            Some(h) => h,
+            None => return,
+        };
+        if let Some(last) = history.back() {
+            let mut s = last.clone();
+            s.timestamp = Local::now().timestamp_millis();
+            s.aviliability = false;
+            s.dealing = 0;
+            history.push_back(s);
+        }
+        if history.len() > HISTORY_LEN {
+            history.pop_front();
+        }
+    }
+
+    // samples of the resource in recent window, from old to new.
+    pub fn window(&self, name: &str, window: Duration) -> Vec<&Sample> {
+        let from = since(window);
+        match self.resources.get(name) {
+            Some(h) => h.iter().filter(|s| s.timestamp >= from).collect(),
+            None => vec![],
+        }
+    }
+
+    pub fn latest(&self, name: &str) -> Option<&Sample> {
+        self.resources.get(name).and_then(|h| h.back())
+    }
+
+    pub fn aggregate(&self, name: &str, window: Duration) -> Option<Aggregate> {
+        let samples = self.window(name, window);
+        if samples.is_empty() {
+            return None;
+        }
+        let n = samples.len();
+        Some(Aggregate {
+            samples: n,
+            availability: samples.iter().filter(|s| s.aviliability).count() as f32 / n as f32,
+            mean_dealing: samples.iter().map(|s| s.dealing as f64).sum::<f64>() / n as f64,
+            max_dealing: samples.iter().map(|s| s.dealing).max().unwrap_or(0),
+            mean_busy_time: samples.iter().map(|s| s.busy_time).sum::<Duration>() / n as u32,
+            mean_average_time: samples.iter().map(|s| s.average_time).sum::<Duration>() / n as u32,
+            distance: samples.windows(2).map(|w| distance(&w[0].position, &w[1].position)).sum(),
+            dealing_trend: trend(&samples),
+            field_means: field_means(&samples),
+        })
+    }
+
+    // timestamp of the first sample of the current unavailable period, None if it is available.
+    pub fn unavailable_since(&self, name: &str) -> Option<i64> {
+        let history = self.resources.get(name)?;
+        let mut since = None;
+        for s in history.iter().rev() {
+            if s.aviliability {
+                break;
+            }
+            since = Some(s.timestamp);
+        }
+        since
+    }
+
+    // timestamps when availability of the resource changed in the window, with the new availability.
+    pub fn transitions(&self, name: &str, window: Duration) -> Vec<(i64, bool)> {
+        self.window(name, window).windows(2)
+            .filter(|w| w[0].aviliability != w[1].aviliability)
+            .map(|w| (w[1].timestamp, w[1].aviliability))
+            .collect()
+    }
+}
@@ -0,0 +1,56 @@
+// simulate resources of the catalog as the scenario says.
+// usage: simulator [scenario file], SCENARIO_FILE is used if not given.
+
+use log::{error, info};
+use tapeos::{
+    base::rule::keep_rules,
+    components::linkhub::internet::seek::seek,
+    tools::{
+        idgen::init_id_generator,
+        rserver::tape_server,
+        simulator::{Scenario, SCENARIO_FILE},
+        wserver::event_server,
+    },
+};
+
+#[tokio::main]
+async fn main() {
+    env_logger::init();
+    init_id_generator();
+
+    let path = std::env::args().nth(1).unwrap_or(SCENARIO_FILE.to_string());
+    let scenario = match Scenario::load(&path) {
+        Ok(s) => s,
+        Err(e) => {
+            error!("load scenario {} error: {}", path, e);
+            return;
+        },
+    };
+
+    if scenario.with_tape() {
+        // register server block on its socket, keep it off the runtime.
+        std::thread::spawn(tape_server);
+        tokio::spawn(keep_rules());
+        tokio::spawn(async {
+            let _ = event_server().await;
+        });
+        tokio::spawn(async move {
+            let _ = seek().await;
+        });
+    }
+
+    let handles = match scenario.start() {
+        Ok(h) => h,
+        Err(e) => {
+            error!("start scenario error: {}", e);
+            return;
+        },
+    };
+    match scenario.get_duration() {
+        Some(d) => tokio::time::sleep(d).await,
+        None => {
+            futures::future::join_all(handles).await;
+        },
+    }
+    info!("simulator: scenario ended");
+}
@@ -3,7 +3,7 @@ use bluer::{
     Address, Device, DeviceProperty, 
     gatt::remote::{Characteristic, Service}
 };
-use crate::base::resource::{Interpreter, Resource, ResourceAddress, Status};
+use crate::base::{capability::Capability, resource::{Interpreter, Resource, ResourceAddress, Status}};
 
 pub struct BluetoothResource {
     name: String,
@@ -11,6 +11,7 @@ pub struct BluetoothResource {
     address: Address,
     description: String,
     interpreter: Interpreter, 
+    capability: Capability,
 
     device: Device,
     props: Vec<DeviceProperty>,
@@ -35,6 +36,7 @@ impl BluetoothResource {
             status: Status::new(true, (0.0, 0.0, 0.0), Duration::from_secs(0)), 
             description: "".to_string(), 
             interpreter: Interpreter::None, 
+            capability: Capability::default(),
         }
     }
 
@@ -94,6 +96,14 @@ impl Resource for BluetoothResource {
         self.description = description;
     }
 
+    fn get_capability(&self) -> &Capability {
+        &self.capability
+    }
+
+    fn set_capability(&mut self, capability: Capability) {
+        self.capability = capability;
+    }
+
     fn get_interpreter(&self) -> &Interpreter {
         &self.interpreter
     }
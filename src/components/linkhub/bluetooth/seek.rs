@@ -2,12 +2,12 @@
 
 use bluer::{
     Device, AdapterEvent, 
-    gatt::remote::{Characteristic, Service}
+    gatt::{WriteOp, remote::{Characteristic, CharacteristicWriteRequest, Service}}
 };
 use std::{
     collections::HashMap, sync::Arc, time::Duration 
 };
-use futures::{pin_mut, StreamExt, future};
+use futures::{pin_mut, StreamExt, future::{self, BoxFuture}};
 use tokio::{
     io::{AsyncBufReadExt, BufReader}, sync::Mutex, time::{interval, sleep}
 
@@ -15,8 +15,8 @@ use tokio::{
 
 use crate::{
     base::{ 
-        intent::{Intent, IntentSource, IntentType}, resource::{Interpreter, Position, Resource}
-    }, components::linkhub::{bluetooth::resource::BluetoothResource, seeker::{send_intent, BLUETOOTH_RESOURCES, RESPONSE_QUEUE, SEEK_RECV}}, core::inxt::intent::handler, tools::llmq
+        intent::{Intent, IntentSource, IntentType}, message::MessageType, resource::{Interpreter, Position, ResourceAddress}
+    }, components::linkhub::{bluetooth::resource::BluetoothResource, registry::{Handle, Liveness, Transport, RESOURCES}, seeker::{send_intent, RESPONSE_QUEUE, SEEK_RECV}}, core::inxt::intent::handler, tools::llmq
 };
 
 use crate::base::errort::BoxResult;
@@ -110,7 +110,9 @@ async fn seek_bluetooth_linux() -> bluer::Result<()> {
             },
             // check resource action
             _ = interval.tick() => {
-                check_resources().await?;
+                if let Err(err) = check_resources().await {
+                    println!("check resources failed: {}", err);
+                }
             },
             // check waiter request
             request = async {
@@ -135,28 +137,30 @@ const TAPE_SERVICE_UUID: uuid::Uuid = uuid::Uuid::from_u128(0x00001234_0000_1000
 const TAPE_CHARACTERISTIC_UUID: uuid::Uuid = uuid::Uuid::from_u128(0x00005678_0000_1000_8000_00805f9b34fb); // Example UUID
 const RETRIES: u8 = 2;
 
-async fn check_resources() -> bluer::Result<()> {
-    let resources = BLUETOOTH_RESOURCES.lock().await;
-    for (_, resource) in resources.iter() {
-        let r = resource.lock().await;
-        let _ = query_status(r.get_name()).await;
-        let char = r.get_char().as_ref().unwrap();
-        if char.flags().await?.read {
-            let (key, value) = receive_message(Arc::clone(resource)).await?;
-            match key.as_str() {
-                "Intent" => {
-                    let intent = receive_intent(value, Arc::clone(resource)).await?;
-                    handler(intent).await;
-                }
-                "Response" => {
-                    let response = receive_response(value).await?;
-                    store_response(response).await?;
-                }
-                _ => (),
+// poll bluetooth resources of the registry, the registry is not locked while they are read.
+async fn check_resources() -> BoxResult<()> {
+    let resources = RESOURCES.lock().await.handles();
+    for (name, resource) in resources {
+        if !matches!(resource.lock().await.get_address(), ResourceAddress::Bluetooth(_)) {
+            continue;
+        }
+        let _ = query_status(&name).await;
+        let (key, value) = match receive_message(&resource).await? {
+            Some(m) => m,
+            None => continue,
+        };
+        match key.as_str() {
+            "Intent" => {
+                let intent = receive_intent(value, name).await?;
+                handler(intent).await;
+            }
+            "Response" => {
+                let response = receive_response(value).await?;
+                store_response(response).await?;
             }
+            _ => (),
         }
     }
-    println!("check resources {} times", BLUETOOTH_RESOURCES.lock().await.len());
     Ok(())
 }
 
@@ -191,8 +195,10 @@ async fn find_tape_characteristic(device: Device) -> bluer::Result<bluer::Result
                     let uuid = cha.uuid().await?;
                     if TAPE_CHARACTERISTIC_UUID == uuid {
                         let c = cha.clone();
-                        store_resource(device, cha, service).await?;
-                        return Ok(Ok(Some(c)));
+                        if store_resource(device, cha, service).await? {
+                            return Ok(Ok(Some(c)));
+                        }
+                        return Ok(Ok(None));
                     }
                 }
             }
@@ -202,8 +208,9 @@ async fn find_tape_characteristic(device: Device) -> bluer::Result<bluer::Result
     Ok(Ok(None))
 }
 
-// create new resource and store the bluetooth device properties into the resource pool
-async fn store_resource(device: Device, cha: Characteristic, service: Service) -> bluer::Result<()> {
+// create new resource and store the bluetooth device properties into the resource pool,
+// false if the device does not tell what it is.
+async fn store_resource(device: Device, cha: Characteristic, service: Service) -> bluer::Result<bool> {
     let props = device.all_properties().await?;
     let name = device.name().await?.unwrap_or_default();
     let resource = BluetoothResource::new(
@@ -213,26 +220,32 @@ async fn store_resource(device: Device, cha: Characteristic, service: Service) -
         Some(service),
         Some(cha),
     );
-    let r = Arc::new(Mutex::new(resource));
-    let r_copy = Arc::clone(&r);
-    BLUETOOTH_RESOURCES.lock().await.insert(name, r);
-    complete_resource(r_copy).await?;
-    Ok(())
+    let r: Handle = Arc::new(Mutex::new(resource));
+    RESOURCES.lock().await.insert(name.clone(), Arc::clone(&r));
+    if let Err(err) = complete_resource(&name, &r).await {
+        println!("    complete {} failed: {}", name, err);
+        remove_resource(name).await;
+        return Ok(false);
+    }
+    Ok(true)
 }
 
 async fn remove_resource(name: String) {
-    BLUETOOTH_RESOURCES.lock().await.remove(&name);
+    RESOURCES.lock().await.remove(&name);
 }
 
 // complete the resource by the resource pool
-async fn complete_resource(blue_resource: Arc<Mutex<BluetoothResource>>) -> bluer::Result<()> {
-    let _ = query_status(blue_resource.lock().await.get_name()).await;
+async fn complete_resource(name: &str, blue_resource: &Handle) -> BoxResult<()> {
+    let _ = query_status(name).await;
     let value: String;
     loop {
-        let (k, v) = receive_message(Arc::clone(&blue_resource)).await?;
-        if k == "Response" {
-            value = v;
-            break;
+        match receive_message(blue_resource).await? {
+            Some((k, v)) if k == "Response" => {
+                value = v;
+                break;
+            },
+            Some(_) => (),
+            None => return Err("characteristic of tape is not readable".into()),
         }
     }
     let response = receive_response(value).await?;
@@ -259,26 +272,26 @@ pub async fn query_status(resource: &str) -> BoxResult<()> {
     Ok(())
 }
 
-pub async fn receive_message(blue_resource: Arc<Mutex<BluetoothResource>>) -> bluer::Result<(String, String)> {
-    let b_resource = blue_resource.lock().await;
-    let char = b_resource.get_char().as_ref().unwrap();
-    let data = char.read().await?;
-    let raw = String::from_utf8(data).unwrap();
+// None if the resource has nothing to read.
+pub async fn receive_message(blue_resource: &Handle) -> BoxResult<Option<(String, String)>> {
+    let raw = match blue_resource.lock().await.receive().await? {
+        Some(raw) => raw,
+        None => return Ok(None),
+    };
     let parts = raw.splitn(2, ':').collect::<Vec<&str>>();
     if parts.len() < 2 {
         // if there is no specific format, use it as intent.
-        return Ok(("Intent".to_string(), parts[0].to_string()))
+        return Ok(Some(("Intent".to_string(), parts[0].to_string())))
     }
-    Ok((parts[0].to_string(), parts[1].to_string()))
+    Ok(Some((parts[0].to_string(), parts[1].to_string())))
 }
 
-pub async fn receive_intent(raw_intent: String, blue_resource: Arc<Mutex<BluetoothResource>>) -> bluer::Result<Intent> {
-    let r = blue_resource.lock().await;
+pub async fn receive_intent(raw_intent: String, name: String) -> bluer::Result<Intent> {
     let intent = Intent::new(
         raw_intent,
         IntentSource::Resource, 
         IntentType::Intent,
-        Some(r.get_name().to_string())
+        Some(name)
     );
 
     Ok(intent)
@@ -335,3 +348,45 @@ fn parse_request(request: String) -> HashMap<String, String> {
     }
     map
 }
+
+impl Transport for BluetoothResource {
+    fn send<'a>(&'a self, body: &'a str, m_type: MessageType, id: Option<i64>) -> BoxFuture<'a, BoxResult<()>> {
+        Box::pin(async move {
+            let message = self.encode(body, m_type, id).await?;
+            self.send_raw(&message).await
+        })
+    }
+
+    // write to the characteristic of tape, with response if the device support it.
+    fn send_raw<'a>(&'a self, command: &'a str) -> BoxFuture<'a, BoxResult<()>> {
+        Box::pin(async move {
+            let char = self.get_char().as_ref().ok_or("no characteristic of tape")?;
+            let flags = char.flags().await?;
+            let op_type = if flags.write {
+                WriteOp::Request
+            } else if flags.write_without_response {
+                WriteOp::Command
+            } else {
+                return Err("characteristic of tape is not writable".into());
+            };
+            let request = CharacteristicWriteRequest { op_type, ..Default::default() };
+            char.write_ext(command.as_bytes(), &request).await?;
+            Ok(())
+        })
+    }
+
+    fn liveness(&self) -> Liveness {
+        Liveness::Connection
+    }
+
+    fn receive(&self) -> BoxFuture<'_, BoxResult<Option<String>>> {
+        Box::pin(async move {
+            let char = self.get_char().as_ref().ok_or("no characteristic of tape")?;
+            if !char.flags().await?.read {
+                return Ok(None);
+            }
+            let data = char.read().await?;
+            Ok(Some(String::from_utf8(data)?))
+        })
+    }
+}
@@ -0,0 +1,85 @@
+use std::{fmt, net::SocketAddr, time::Duration};
+use serde::{Deserialize, Serialize};
+use crate::base::{capability::Capability, resource::{Interpreter, Resource, ResourceAddress, Status}};
+
+#[derive(Serialize, Deserialize)]
+pub struct CoapResource {
+    name: String,
+    status: Status,
+    description: String,
+    address: SocketAddr,
+    interpreter: Interpreter,
+    #[serde(default)]
+    capability: Capability,
+}
+
+impl CoapResource {
+    pub fn new(name: String, description: String, address: SocketAddr) -> Self {
+        Self {
+            name, description, address,
+            status: Status::new(true, (0.0, 0.0, 0.0), Duration::from_secs(0)),
+            interpreter: Interpreter::None,
+            capability: Capability::default(),
+        }
+    }
+
+    pub fn get_address(&self) -> &SocketAddr {
+        &self.address
+    }
+}
+
+impl Resource for CoapResource {
+    fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    fn get_address(&self) -> ResourceAddress {
+        ResourceAddress::Coap(self.address)
+    }
+
+    fn get_status(&mut self) -> &mut Status {
+        &mut self.status
+    }
+
+    fn get_description(&self) -> &str {
+        &self.description
+    }
+
+    fn display_status(&self) -> String {
+        format!("{:?}", self.status)
+    }
+
+    fn set_status(&mut self, status: Status) {
+        self.status = status
+    }
+
+    fn set_interpreter(&mut self, interpreter: Interpreter) {
+        self.interpreter = interpreter;
+    }
+
+    fn set_description(&mut self, description: String) {
+        self.description = description;
+    }
+
+    fn get_capability(&self) -> &Capability {
+        &self.capability
+    }
+
+    fn set_capability(&mut self, capability: Capability) {
+        self.capability = capability;
+    }
+
+    fn get_interpreter(&self) -> &Interpreter {
+        &self.interpreter
+    }
+
+    fn is_interpreter_none(&self) -> bool {
+        matches!(self.interpreter, Interpreter::None)
+    }
+}
+
+impl fmt::Display for CoapResource {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}/{}/{};", self.get_name(), self.get_description(), self.display_status())
+    }
+}
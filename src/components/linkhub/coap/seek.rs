@@ -0,0 +1,321 @@
+// serve constrained resources by CoAP, which is much lighter than BLE or json over UDP.
+// resource -> TAPE:
+//   POST /rd?ep=<name>       register as resource-directory does, payload is description of the resource,
+//                            or `{"description": .., "capability": ..}` to declare its capability manifest.
+//                            register again from the same address update them, answered with 2.04 Changed.
+//   POST /result             `Message` json of Response or Reject with id of the sub-intent.
+// TAPE -> resource:
+//   POST /intent             confirmable, sub-intent as `Message` json, or `command:id` for resource with interpreter.
+//                            error response or no acknowledgement after retransmission means reject.
+//   GET /status, Observe=0   resource notify `Status` json whenever it changes.
+
+use std::{
+    collections::HashMap,
+    net::SocketAddr,
+    sync::{atomic::{AtomicU16, Ordering}, Arc},
+    time::Duration,
+};
+use coap_lite::{CoapOption, MessageClass, MessageType as CoapType, Packet, RequestType, ResponseType};
+use futures::future::BoxFuture;
+use lazy_static::lazy_static;
+use log::{info, warn};
+use rand::Rng;
+use serde::Deserialize;
+use tokio::{
+    net::UdpSocket,
+    sync::{mpsc::{self, UnboundedSender}, oneshot, Mutex},
+    time::timeout,
+};
+
+use crate::{
+    base::{
+        capability::Capability,
+        errort::BoxResult,
+        message::{Message, MessageType},
+        resource::{Resource, ResourceAddress, Status},
+    },
+    components::linkhub::{
+        coap::resource::CoapResource,
+        internet::seek::{mark_complete, reroute_rejected},
+        registry::{Liveness, Transport, RESOURCES},
+        seeker::{fresh_resource_status, remove_resource_by_name},
+    },
+};
+
+pub const COAP_ADDRESS: &str = "127.0.0.1:5683";
+// transmission parameters of RFC 7252.
+const ACK_TIMEOUT: Duration = Duration::from_secs(2);
+const MAX_RETRANSMIT: u32 = 4;
+
+lazy_static! {
+    pub static ref COAP_SOCKET: Mutex<Option<Arc<UdpSocket>>> = Mutex::new(None);
+    // message id of confirmable request -> waiter of its acknowledgement.
+    static ref PENDING: Mutex<HashMap<u16, oneshot::Sender<Packet>>> = Mutex::new(HashMap::new());
+    // token of observe request -> name of resource.
+    static ref OBSERVING: Mutex<HashMap<Vec<u8>, String>> = Mutex::new(HashMap::new());
+    static ref MESSAGE_ID: AtomicU16 = AtomicU16::new(rand::thread_rng().gen());
+    // sub-intents rejected by resources, they are rerouted by the seeker loop.
+    static ref REJECTED: Mutex<Option<UnboundedSender<i64>>> = Mutex::new(None);
+}
+
+#[derive(Deserialize)]
+struct Registration {
+    description: String,
+    #[serde(default)]
+    capability: Capability,
+}
+
+pub async fn seek() -> BoxResult<()> {
+    let socket = Arc::new(UdpSocket::bind(COAP_ADDRESS).await?);
+    COAP_SOCKET.lock().await.replace(Arc::clone(&socket));
+    info!("coap seeker listen on {}", COAP_ADDRESS);
+
+    let (tx, mut rx) = mpsc::unbounded_channel::<i64>();
+    REJECTED.lock().await.replace(tx);
+
+    let mut buf = [0; 1500];
+    loop {
+        tokio::select! {
+            r = socket.recv_from(&mut buf) => {
+                let (amt, src) = r?;
+                let packet = match Packet::from_bytes(&buf[..amt]) {
+                    Ok(p) => p,
+                    Err(e) => {
+                        warn!("coap packet from {} error: {}", src, e);
+                        continue;
+                    },
+                };
+                tokio::spawn(async move {
+                    if let Err(e) = packet_handler(packet, src).await {
+                        warn!("coap handler error: {}", e);
+                    }
+                });
+            },
+            Some(id) = rx.recv() => {
+                tokio::spawn(async move {
+                    let _ = reroute_rejected(id).await;
+                });
+            },
+        }
+    }
+}
+
+async fn packet_handler(packet: Packet, src: SocketAddr) -> BoxResult<()> {
+    match packet.header.code {
+        MessageClass::Request(RequestType::Post) => {
+            match uri_path(&packet).as_str() {
+                "rd" => register(&packet, src).await,
+                "result" => result(&packet, src).await,
+                _ => respond(&packet, src, ResponseType::NotFound, vec![]).await,
+            }
+        },
+        MessageClass::Request(_) => respond(&packet, src, ResponseType::MethodNotAllowed, vec![]).await,
+        _ => {
+            let t = packet.header.get_type();
+            if t == CoapType::Acknowledgement || t == CoapType::Reset {
+                if let Some(tx) = PENDING.lock().await.remove(&packet.header.message_id) {
+                    let _ = tx.send(packet);
+                    return Ok(());
+                }
+            }
+            notification(packet, src).await
+        },
+    }
+}
+
+async fn register(packet: &Packet, src: SocketAddr) -> BoxResult<()> {
+    let name = match uri_query(packet, "ep") {
+        Some(n) => n,
+        None => return respond(packet, src, ResponseType::BadRequest, b"ep is required".to_vec()).await,
+    };
+    let registration = match serde_json::from_slice::<Registration>(&packet.payload) {
+        Ok(r) => r,
+        Err(_) => Registration { description: String::from_utf8(packet.payload.clone())?, capability: Capability::default() },
+    };
+    let mut rs = RESOURCES.lock().await;
+    if let Some(handle) = rs.get(&name) {
+        drop(rs);
+        // same device register again to update itself, or another device using the same name.
+        let mut r = handle.lock().await;
+        if r.get_address() != ResourceAddress::Coap(src) {
+            drop(r);
+            return respond(packet, src, ResponseType::Conflict, vec![]).await;
+        }
+        r.set_description(registration.description);
+        r.set_capability(registration.capability);
+        drop(r);
+        info!("coap resource {} updated from {}", name, src);
+        return respond(packet, src, ResponseType::Changed, vec![]).await;
+    }
+    let mut r = CoapResource::new(name.clone(), registration.description, src);
+    r.set_capability(registration.capability);
+    rs.insert(name.clone(), Arc::new(Mutex::new(r)));
+    drop(rs);
+    info!("coap resource {} registered from {}", name, src);
+    respond(packet, src, ResponseType::Created, vec![]).await?;
+
+    tokio::spawn(async move {
+        if let Err(e) = observe_status(name.clone(), src).await {
+            warn!("observe status of {} failed: {}", name, e);
+        }
+    });
+    Ok(())
+}
+
+async fn result(packet: &Packet, src: SocketAddr) -> BoxResult<()> {
+    let m: Message = match serde_json::from_slice(&packet.payload) {
+        Ok(m) => m,
+        Err(_) => return respond(packet, src, ResponseType::BadRequest, vec![]).await,
+    };
+    respond(packet, src, ResponseType::Changed, vec![]).await?;
+    let id = m.get_id().ok_or("result without id")?;
+    match m.get_type() {
+        MessageType::Response => mark_complete(id).await,
+        MessageType::Reject => reroute_rejected(id).await,
+        _ => {
+            warn!("do not support such result from {}", src);
+            Ok(())
+        },
+    }
+}
+
+// register as an observer of resource status, the first status come with the acknowledgement.
+async fn observe_status(name: String, addr: SocketAddr) -> BoxResult<()> {
+    let token = rand::thread_rng().gen::<[u8; 4]>().to_vec();
+    let mut p = new_packet(CoapType::Confirmable, MessageClass::Request(RequestType::Get), token.clone());
+    p.add_option(CoapOption::UriPath, b"status".to_vec());
+    p.set_observe_value(0);
+    OBSERVING.lock().await.insert(token, name);
+    let ack = request(addr, p).await?;
+    notification(ack, addr).await
+}
+
+async fn notification(packet: Packet, src: SocketAddr) -> BoxResult<()> {
+    if packet.header.get_type() == CoapType::Confirmable {
+        let mut ack = new_packet(CoapType::Acknowledgement, MessageClass::Empty, vec![]);
+        ack.header.message_id = packet.header.message_id;
+        send_packet(&ack, src).await?;
+    }
+    let name = match OBSERVING.lock().await.get(packet.get_token()) {
+        Some(n) => n.clone(),
+        // empty acknowledgement of separate response.
+        None => return Ok(()),
+    };
+    match packet.header.code {
+        MessageClass::Response(ResponseType::Content) => {
+            let s: Status = serde_json::from_slice(&packet.payload)?;
+            fresh_resource_status(&name, s).await;
+        },
+        MessageClass::Response(_) => {
+            // resource cancel the observation, which means it is leaving.
+            info!("coap resource {} stop notify status", name);
+            OBSERVING.lock().await.remove(packet.get_token());
+            remove_resource_by_name(&name).await;
+        },
+        _ => (),
+    }
+    Ok(())
+}
+
+impl Transport for CoapResource {
+    fn send<'a>(&'a self, body: &'a str, m_type: MessageType, id: Option<i64>) -> BoxFuture<'a, BoxResult<()>> {
+        Box::pin(async move {
+            let message = self.encode(body, m_type, id).await?;
+            post_intent(*self.get_address(), self.get_name().to_string(), message.into_bytes(), id).await
+        })
+    }
+
+    fn send_raw<'a>(&'a self, command: &'a str) -> BoxFuture<'a, BoxResult<()>> {
+        Box::pin(post_intent(*self.get_address(), self.get_name().to_string(), command.as_bytes().to_vec(), None))
+    }
+
+    fn liveness(&self) -> Liveness {
+        Liveness::Connection
+    }
+}
+
+// post payload to /intent of the resource, the sub-intent with id will be rerouted if it is rejected.
+pub async fn post_intent(addr: SocketAddr, name: String, payload: Vec<u8>, id: Option<i64>) -> BoxResult<()> {
+    let token = rand::thread_rng().gen::<[u8; 4]>().to_vec();
+    let mut p = new_packet(CoapType::Confirmable, MessageClass::Request(RequestType::Post), token);
+    p.add_option(CoapOption::UriPath, b"intent".to_vec());
+    p.payload = payload;
+
+    // the acknowledgement may come after several retransmissions, so wait it in background.
+    tokio::spawn(async move {
+        let rejected = match request(addr, p).await {
+            Ok(ack) => match ack.header.code {
+                MessageClass::Response(code) => code.is_error(),
+                _ => ack.header.get_type() == CoapType::Reset,
+            },
+            Err(e) => {
+                warn!("coap resource {} no response: {}", name, e);
+                true
+            },
+        };
+        if let (true, Some(id)) = (rejected, id) {
+            if let Some(tx) = REJECTED.lock().await.as_ref() {
+                let _ = tx.send(id);
+            }
+        }
+    });
+    Ok(())
+}
+
+// send confirmable request and wait for the acknowledgement with exponential back-off.
+async fn request(addr: SocketAddr, packet: Packet) -> BoxResult<Packet> {
+    let (tx, mut rx) = oneshot::channel();
+    PENDING.lock().await.insert(packet.header.message_id, tx);
+    let mut wait = ACK_TIMEOUT;
+    for _ in 0..=MAX_RETRANSMIT {
+        send_packet(&packet, addr).await?;
+        if let Ok(ack) = timeout(wait, &mut rx).await {
+            return Ok(ack?);
+        }
+        wait *= 2;
+    }
+    PENDING.lock().await.remove(&packet.header.message_id);
+    Err("coap request timeout".into())
+}
+
+async fn respond(request: &Packet, src: SocketAddr, code: ResponseType, payload: Vec<u8>) -> BoxResult<()> {
+    let t = match request.header.get_type() {
+        CoapType::Confirmable => CoapType::Acknowledgement,
+        _ => CoapType::NonConfirmable,
+    };
+    let mut p = new_packet(t, MessageClass::Response(code), request.get_token().to_vec());
+    if t == CoapType::Acknowledgement {
+        p.header.message_id = request.header.message_id;
+    }
+    p.payload = payload;
+    send_packet(&p, src).await
+}
+
+fn new_packet(t: CoapType, code: MessageClass, token: Vec<u8>) -> Packet {
+    let mut p = Packet::new();
+    p.header.set_type(t);
+    p.header.code = code;
+    p.header.message_id = MESSAGE_ID.fetch_add(1, Ordering::Relaxed);
+    p.set_token(token);
+    p
+}
+
+async fn send_packet(p: &Packet, addr: SocketAddr) -> BoxResult<()> {
+    let socket = COAP_SOCKET.lock().await.clone().ok_or("coap seeker is not started")?;
+    socket.send_to(&p.to_bytes()?, addr).await?;
+    Ok(())
+}
+
+fn uri_path(p: &Packet) -> String {
+    match p.get_option(CoapOption::UriPath) {
+        Some(segments) => segments.iter().map(|s| String::from_utf8_lossy(s)).collect::<Vec<_>>().join("/"),
+        None => "".to_string(),
+    }
+}
+
+fn uri_query(p: &Packet, key: &str) -> Option<String> {
+    p.get_option(CoapOption::UriQuery)?
+        .iter()
+        .filter_map(|q| String::from_utf8(q.clone()).ok())
+        .find_map(|q| q.strip_prefix(&format!("{key}=")).map(|v| v.to_string()))
+}
@@ -0,0 +1,162 @@
+// in this file, we will keep who owns the stable ids of internet resources.
+// TAPE issue a token with the id at the first registration, a resource must give both to register
+// again with the id, so that no one else can take its entry. ids are kept across restart of TAPE
+// in OWNER_FILE, like {"7301": "3f9c0a..."}, and the resource keep its own in RESOURCE_ID_FILE.
+
+use std::{collections::HashMap, fs, io::ErrorKind, path::{Path, PathBuf}};
+use lazy_static::lazy_static;
+use log::{error, warn};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+
+use crate::base::{acl::same_token, errort::BoxResult};
+
+pub const OWNER_FILE: &str = "owners.json";
+pub const RESOURCE_ID_FILE: &str = "resource_id.json";
+
+lazy_static! {
+    pub static ref OWNERS: Mutex<Owners> = Mutex::new(Owners::load(OWNER_FILE));
+    // what TAPE gave to resources of this process, by name of resource.
+    pub static ref CREDENTIALS: Mutex<Credentials> = Mutex::new(Credentials::load(RESOURCE_ID_FILE));
+}
+
+pub struct Owners {
+    tokens: HashMap<i64, String>,
+    path: Option<PathBuf>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Credential {
+    pub id: i64,
+    pub token: String,
+}
+
+pub struct Credentials {
+    credentials: HashMap<String, Credential>,
+    path: Option<PathBuf>,
+}
+
+fn new_token() -> String {
+    rand::thread_rng().gen::<[u8; 16]>().iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+// missing file is empty, broken one is kept aside and never overwritten.
+fn load_map<T: for<'de> Deserialize<'de> + Default>(path: &Path) -> T {
+    let result: BoxResult<T> = fs::read_to_string(path).map_err(|e| e.into()).and_then(|d| Ok(serde_json::from_str(&d)?));
+    match result {
+        Ok(m) => m,
+        Err(e) if e.downcast_ref::<std::io::Error>().is_some_and(|e| e.kind() == ErrorKind::NotFound) => T::default(),
+        Err(e) => {
+            let bad = path.with_extension("json.bad");
+            error!("load {} error: {}, it is moved to {}", path.display(), e, bad.display());
+            if let Err(e) = fs::rename(path, &bad) {
+                error!("move {} error: {}", path.display(), e);
+            }
+            T::default()
+        },
+    }
+}
+
+fn save_map<T: Serialize>(path: &Option<PathBuf>, map: &T) {
+    let path = match path {
+        Some(p) => p,
+        None => return,
+    };
+    let result: BoxResult<()> = serde_json::to_string_pretty(map).map_err(|e| e.into()).and_then(|d| {
+        let tmp = path.with_extension("tmp");
+        fs::write(&tmp, d)?;
+        fs::rename(&tmp, path)?;
+        Ok(())
+    });
+    if let Err(e) = result {
+        warn!("save {} error: {}", path.display(), e);
+    }
+}
+
+impl Owners {
+    pub fn new(path: Option<PathBuf>) -> Self {
+        Self { tokens: HashMap::new(), path }
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Self {
+        let path = path.as_ref();
+        Self { tokens: load_map(path), path: Some(path.to_path_buf()) }
+    }
+
+    // whether the token shows the id is owned, an id we never issued is free, like one given by another TAPE.
+    pub fn verify(&self, id: i64, token: Option<&str>) -> bool {
+        match self.tokens.get(&id) {
+            Some(t) => token.is_some_and(|k| same_token(k, t)),
+            None => true,
+        }
+    }
+
+    // token of the id, the given one or a new one is kept if the id is free.
+    pub fn own(&mut self, id: i64, token: Option<&str>) -> String {
+        if let Some(t) = self.tokens.get(&id) {
+            return t.clone();
+        }
+        let t = token.map(|t| t.to_string()).unwrap_or_else(new_token);
+        self.tokens.insert(id, t.clone());
+        save_map(&self.path, &self.tokens);
+        t
+    }
+}
+
+impl Credentials {
+    pub fn load<P: AsRef<Path>>(path: P) -> Self {
+        let path = path.as_ref();
+        Self { credentials: load_map(path), path: Some(path.to_path_buf()) }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Credential> {
+        self.credentials.get(name)
+    }
+
+    pub fn insert(&mut self, name: String, credential: Credential) {
+        self.credentials.insert(name, credential);
+        save_map(&self.path, &self.credentials);
+    }
+
+    pub fn remove(&mut self, name: &str) {
+        if self.credentials.remove(name).is_some() {
+            save_map(&self.path, &self.credentials);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn token_is_needed_for_owned_id() {
+        let mut owners = Owners::new(None);
+        assert!(owners.verify(7, None));
+        let token = owners.own(7, None);
+        assert_eq!(token.len(), 32);
+        assert!(owners.verify(7, Some(&token)));
+        assert!(!owners.verify(7, None));
+        assert!(!owners.verify(7, Some("0123456789abcdef0123456789abcdef")));
+        // owning again does not change the token.
+        assert_eq!(owners.own(7, Some("other")), token);
+    }
+
+    #[test]
+    fn free_id_keeps_given_token() {
+        let mut owners = Owners::new(None);
+        assert_eq!(owners.own(8, Some("handed-over")), "handed-over");
+        assert!(owners.verify(8, Some("handed-over")));
+    }
+
+    #[test]
+    fn owners_are_kept_in_file() {
+        let path = std::env::temp_dir().join(format!("tape-owners-{}.json", std::process::id()));
+        let token = Owners::load(&path).own(9, None);
+        let owners = Owners::load(&path);
+        fs::remove_file(&path).unwrap();
+        assert!(owners.verify(9, Some(&token)));
+        assert!(!owners.verify(9, None));
+    }
+}
@@ -1,20 +1,25 @@
 use std::{fmt, net::SocketAddr};
 use serde::{Deserialize, Serialize};
-use crate::base::resource::{Interpreter, Resource, ResourceAddress, Status};
+use crate::base::{capability::Capability, resource::{Interpreter, Resource, ResourceAddress, Status}};
 
 #[derive(Serialize, Deserialize)]
 pub struct InternetResource {
+    // stable id given by TAPE, name is only used to display and may change.
+    #[serde(default)]
+    id: Option<i64>,
     name: String,
     status: Status,
     description: String,
     address: SocketAddr,
     interpreter: Interpreter,
+    #[serde(default)]
+    capability: Capability,
 }
 
 impl InternetResource {
     pub fn new(name: String, description: String, address: SocketAddr, status: Status) -> Self {
         Self {
-            name, description, address, status, interpreter: Interpreter::None
+            id: None, name, description, address, status, interpreter: Interpreter::None, capability: Capability::default()
         }
     }
 
@@ -25,6 +30,14 @@ impl InternetResource {
     pub fn set_address(&mut self, addr: SocketAddr) {
         self.address = addr;
     }
+
+    pub fn get_id(&self) -> Option<i64> {
+        self.id
+    }
+
+    pub fn set_id(&mut self, id: i64) {
+        self.id = Some(id);
+    }
 }
 
 impl Resource for InternetResource {
@@ -60,6 +73,14 @@ impl Resource for InternetResource {
         self.description = description;
     }
 
+    fn get_capability(&self) -> &Capability {
+        &self.capability
+    }
+
+    fn set_capability(&mut self, capability: Capability) {
+        self.capability = capability;
+    }
+
     fn get_interpreter(&self) -> &Interpreter {
         &self.interpreter
     }
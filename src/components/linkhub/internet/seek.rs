@@ -9,27 +9,41 @@ use std::{
 use log::{info, error, warn};
 use tokio::{
     net::UdpSocket,
-    time::{Duration, interval},
+    time::{Duration, interval, timeout},
     sync::Mutex,
     sync::mpsc::{self, Receiver, Sender}
 };
 use lazy_static::lazy_static; 
+use futures::future::BoxFuture;
 use crate::{
     base::{
+        capability::Capability,
         errort::BoxResult, 
+        health::{Outcome, HEALTH},
         message::{Message, MessageType}, 
         intent::{Intent, IntentSource, IntentType}, 
-        resource::{Interpreter, RegisterServer, Resource} 
+        region::SERVICE_AREA,
+        resource::{Interpreter, Position, RegisterServer, Resource, ResourceAddress, Status} 
     },
     components::linkhub::{
-        internet::resource::InternetResource,
-        seeker::{reject_intent, INTENT_QUEUE, INTERNET_RESOURCES},
+        internet::{owner::OWNERS, resource::InternetResource},
+        registry::{Liveness, Transport, RESOURCES},
+        seeker::{reject_intent, remove_resource_by_name, INTENT_QUEUE},
     },
     core::inxt::{
         intent::handler, 
         preprocess::JudgeResult, 
         router::reroute
-    }, tools::llmq,
+    }, 
+    tools::{
+        discovery::{advertise_tape, find_register_server, REGISTER},
+        event::{emit, emit_with_cost, EventKind},
+        idgen::{generate_id, IdType},
+        interpreter::command_selector,
+        llmq,
+        ruleadmin::{handle_admin, AdminReply},
+        script::run_script,
+    },
 };
 
 macro_rules! get_udp {
@@ -38,9 +52,9 @@ macro_rules! get_udp {
     }
 }
 
-pub const INPUT_TAPE_ADDRESS: &str = "127.0.0.1:8888";
-pub const TAPE_ADDRESS: &str = "127.0.0.1:8889";
-const V_POSITION: ((f32, f32), (f32, f32), (f32, f32)) = ((-100.0, 100.0), (-100.0, 100.0), (-100.0, 100.0));
+// sockets are bound to all interfaces so that resources on LAN can reach us.
+const INPUT_TAPE_PORT: u16 = 8888;
+const TAPE_PORT: u16 = 8889;
 lazy_static! {
     pub static ref SOCKET: Mutex<Option<UdpSocket>> = Mutex::new(None);
 }
@@ -56,7 +70,7 @@ pub async fn seek() -> BoxResult<()> {
 // act as a listener to receive message
 async fn receive(tx: Sender<(String, SocketAddr)>) {
     tokio::spawn(async move {
-        let socket = UdpSocket::bind(INPUT_TAPE_ADDRESS).await.expect("Failed to bind to socket");
+        let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, INPUT_TAPE_PORT)).await.expect("Failed to bind to socket");
         let mut buf = [0; 8192];
 
         loop {
@@ -73,10 +87,11 @@ async fn receive(tx: Sender<(String, SocketAddr)>) {
 
 async fn response(mut rx: Receiver<(String, SocketAddr)>) -> BoxResult<()> {
     
-    let socket = UdpSocket::bind(TAPE_ADDRESS).await.expect("Failed to bind to socket");
+    let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, TAPE_PORT)).await.expect("Failed to bind to socket");
     SOCKET.lock().await.replace(socket);
     
-    find_register(SOCKET.lock().await.as_ref().unwrap(), true, V_POSITION).await; 
+    find_register(SOCKET.lock().await.as_ref().unwrap(), true).await; 
+    advertise().await;
     let mut heartbeat_inter = interval(Duration::from_secs(20));
     let mut reroute_inter = interval(Duration::from_secs(60));
     let mut status_inter = interval(Duration::from_secs(10));
@@ -107,6 +122,8 @@ async fn response(mut rx: Receiver<(String, SocketAddr)>) -> BoxResult<()> {
             _ = status_inter.tick() => {
                 tokio::spawn(async move {
                     query_status().await.unwrap();
+                    // keep the load we advertise fresh.
+                    advertise().await;
                 });
             },
         }
@@ -114,29 +131,78 @@ async fn response(mut rx: Receiver<(String, SocketAddr)>) -> BoxResult<()> {
 }
 
 
-async fn find_register(socket: &UdpSocket, tape: bool, position: ((f32, f32), (f32, f32), (f32, f32))) {
-    let v4: Ipv4Addr = Ipv4Addr::new(127, 0, 0, 1);
-    let ipv4 = IpAddr::V4(v4);
-    let iaddr = SocketAddr::new(ipv4, 8888);
-    let oaddr = SocketAddr::new(ipv4, 8889);
-    let r = RegisterServer::new(tape, Some(iaddr), Some(oaddr), position);
+// ip is left unspecified, register server takes the one our registration comes from.
+async fn find_register(socket: &UdpSocket, tape: bool) {
+    let iaddr = SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), INPUT_TAPE_PORT);
+    let oaddr = SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), TAPE_PORT);
+    let regions = SERVICE_AREA.lock().await.get_regions().clone();
+    let r = RegisterServer::new(tape, Some(iaddr), Some(oaddr), regions, Position::default(), None);
     let r_json = serde_json::to_string(&r).unwrap();
-    let addr = SocketAddr::new(ipv4, 8000);
+    let addr = find_register_server().await;
     socket.send_to(&r_json.as_bytes(), addr).await.unwrap();
 } 
 
+// advertise ourselves on LAN, so that waiters can find us without register server.
+async fn advertise() {
+    let regions = SERVICE_AREA.lock().await.get_regions().clone();
+    let load = RESOURCES.lock().await.len() as u64;
+    if let Err(e) = advertise_tape(INPUT_TAPE_PORT, TAPE_PORT, &regions, load) {
+        warn!("advertise TAPE error: {}", e);
+    }
+}
+
+// hand the resource which moves out of our region over to the TAPE responsible for its new position.
+// return false if no other TAPE can serve it.
+pub async fn handover(name: &str, status: &Status) -> BoxResult<bool> {
+    const ASK_D: Duration = Duration::from_secs(1);
+    let r = match RESOURCES.lock().await.get(name) {
+        Some(r) => r,
+        None => return Ok(false),
+    };
+    let addr = match r.lock().await.get_address() {
+        ResourceAddress::Internet(a) => a,
+        _ => return Ok(false),
+    };
+    // ask register server with a new socket, so that the reply will not mix with messages of resources.
+    let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)).await?;
+    let ask = RegisterServer::new(false, None, None, Vec::new(), status.get_position().clone(), status.get_place().cloned());
+    let register = *REGISTER.lock().await;
+    socket.connect(register).await?;
+    // register server knows us by the address we reach it from.
+    let own = SocketAddr::new(socket.local_addr()?.ip(), INPUT_TAPE_PORT);
+    socket.send(serde_json::to_string(&ask)?.as_bytes()).await?;
+    let mut buf = [0; 8192];
+    let amt = match timeout(ASK_D, socket.recv(&mut buf)).await {
+        Ok(r) => r?,
+        Err(_) => return Ok(false),
+    };
+    let tape: RegisterServer = serde_json::from_slice(&buf[..amt])?;
+    if tape.get_iaddr() == own {
+        return Ok(false);
+    }
+    let body = serde_json::to_string(&tape)?;
+    let m = Message::new(MessageType::Handover, body, None);
+    get_udp!().send_to(serde_json::to_string(&m)?.as_bytes(), addr).await?;
+    info!("hand {} over to {}", name, tape.get_iaddr());
+    Ok(true)
+}
+
 async fn try_reroute() -> BoxResult<()> {
     const EXPIRE_D: Duration = Duration::from_secs(60);
     let mut i_q = INTENT_QUEUE.lock().await;
     let mut id = 0;
     for i in i_q.iter_mut() {
         let mut c: bool = false;
+        let i_id = i.get_id();
         for s_i in i.iter_sub_intent() {
             let live = Instant::now() - s_i.get_routed();
             if live > EXPIRE_D {
                 error!("reroute sub_intent: {} {}", s_i.get_description(), s_i.get_selected_resource().unwrap());
+                HEALTH.lock().await.complete(s_i.get_id(), Outcome::Timeout);
                 match reroute(s_i).await {
-                    Ok(()) => {},
+                    Ok(()) => {
+                        emit(EventKind::Rerouted, i_id, Some(s_i.get_id()), s_i.get_selected_resource().map(|r| r.as_str()), s_i.get_description());
+                    },
                     Err(e) => {
                         warn!("{}", e);
                         c = true;
@@ -145,7 +211,8 @@ async fn try_reroute() -> BoxResult<()> {
             }
         }
         if c {
-            reject_intent(i.get_resource().unwrap().to_string(), i.get_description()).await?;
+            reject_intent(i.get_resource().unwrap().to_string(), i.get_description(), None).await?;
+            emit(EventKind::Rejected, i_id, None, None, "no available resource to reroute");
             id = i.get_id();
         }
     }
@@ -177,18 +244,25 @@ async fn message_handler(message: &str, src: SocketAddr) -> BoxResult<()> {
             // info!("get intent: {}", intent.get_description());
 
             match handler(intent).await {
-                JudgeResult::Reject(e) => reject_intent(r.unwrap(), &e).await.unwrap(),
+                JudgeResult::Reject(e, x) => reject_intent(r.unwrap(), &e, x.as_ref()).await.unwrap(),
                 _ => (),
             };
         },
         MessageType::Register => {
-            let r = message2resource(m.get_body())?;
-            let m_body = match store_resource(r).await {
-                _ => "Registerd",
-                // Some(_) => "Registerd",
-                // None => "Duplicate"
+            let mut r = message2resource(m.get_body())?;
+            // resource on LAN only know its local address, we reach it where it comes from.
+            r.set_address(src);
+            let capability = r.get_capability().clone();
+            for p in capability.check_status(r.get_status()) {
+                warn!("status of {}: {}", r.get_name(), p);
+            }
+            let (m_body, id, token) = match store_resource(r, m.get_id(), m.get_token()).await {
+                Registration::New(id, token) => ("Registerd", Some(id), Some(token)),
+                Registration::Renew(id, token) => ("Reregisterd", Some(id), Some(token)),
+                Registration::Conflict => ("Name Conflict", None, None),
+                Registration::Denied => ("Unauthorized", None, None),
             };
-            let m = Message::new(MessageType::Response, m_body.to_string(), None);
+            let m = Message::new(MessageType::Response, m_body.to_string(), id).with_token(token);
             let m_json = serde_json::to_string(&m)?;
             info!("send to src: {}", src);
             
@@ -207,21 +281,16 @@ async fn message_handler(message: &str, src: SocketAddr) -> BoxResult<()> {
             // info!("Send Over: {}", m.get_body());
         },
         MessageType::Reject => {
-            let id = m.get_id().unwrap();
-            for i in INTENT_QUEUE.lock().await.iter_mut() {
-                let i_r = i.get_resource().unwrap().to_string();
-                let i_d = i.get_description().to_string();
-                for ii in i.iter_sub_intent() {
-                    if ii.get_id() != id { continue; }
-                    match reroute(ii).await {
-                        Ok(_) => (),
-                        Err(_) => {
-                            reject_intent(i_r, &i_d).await?;
-                            return Ok(());
-                        },
-                    }
-                }
-            }   
+            reroute_rejected(m.get_id().unwrap()).await?;
+        },
+        MessageType::Admin => {
+            let reply = match find_resource_by_addr(&src).await {
+                Some(r) => handle_admin(&r, m.get_token(), &m.get_body()).await,
+                None => AdminReply::Error("Register First".to_string()),
+            };
+            let m = Message::new(MessageType::Response, serde_json::to_string(&reply)?, m.get_id());
+            let m_json = serde_json::to_string(&m)?;
+            get_udp!().send_to(m_json.as_bytes(), src).await?;
         },
         _ => {
             warn!("no such type");
@@ -230,6 +299,32 @@ async fn message_handler(message: &str, src: SocketAddr) -> BoxResult<()> {
     Ok(())
 }
 
+// reroute the sub intent rejected by its resource, reject the whole intent if no resource left.
+pub async fn reroute_rejected(id: i64) -> BoxResult<()> {
+    HEALTH.lock().await.complete(id, Outcome::Failure);
+    for i in INTENT_QUEUE.lock().await.iter_mut() {
+        let i_id = i.get_id();
+        let i_r = i.get_resource().unwrap().to_string();
+        let i_d = i.get_description().to_string();
+        for ii in i.iter_sub_intent() {
+            if ii.get_id() != id { continue; }
+            // same events as rerouting the sub-intent which is timeout, see try_reroute.
+            match reroute(ii).await {
+                Ok(_) => {
+                    emit(EventKind::Rerouted, i_id, Some(ii.get_id()), ii.get_selected_resource().map(|r| r.as_str()), ii.get_description());
+                },
+                Err(e) => {
+                    warn!("{}", e);
+                    reject_intent(i_r, &i_d, None).await?;
+                    emit(EventKind::Rejected, i_id, None, None, "no available resource to reroute");
+                    return Ok(());
+                },
+            }
+        }
+    }   
+    Ok(())
+}
+
 // assume the message is a Message Serilization if not try to parse it.
 fn parse_message(message: &str) -> Message{
     match serde_json::from_str(message) {
@@ -255,13 +350,74 @@ lazy_static! {
     pub static ref NOW: Arc<Mutex<Instant>> = Arc::new(Mutex::new(Instant::now()));
 }
 
-async fn store_resource(resource: InternetResource) -> Option<()> {
-    let mut i_rs = INTERNET_RESOURCES.lock().await;
-    let name = resource.get_name();
-    if i_rs.get(name).is_some() {return None;}
-    
-    i_rs.insert(name.to_string(), Arc::new(Mutex::new(resource)));
-    Some(())
+enum Registration {
+    New(i64, String),
+    // known resource register again, maybe with new address or description.
+    Renew(i64, String),
+    // name is used by another resource.
+    Conflict,
+    // id is given without the token of its owner.
+    Denied,
+}
+
+// resource is known by the id we gave it, and it must give the token of the id.
+async fn store_resource(mut resource: InternetResource, id: Option<i64>, token: Option<&str>) -> Registration {
+    let name = resource.get_name().to_string();
+    if let Some(i) = id {
+        if !OWNERS.lock().await.verify(i, token) {
+            warn!("resource {} from {} gives id {} without its token", name, resource.get_address(), i);
+            return Registration::Denied;
+        }
+    }
+    // id given by another TAPE is kept after handover.
+    let i = id.unwrap_or_else(|| generate_id(IdType::Resource));
+
+    // registry is not locked while resources are.
+    let handles = RESOURCES.lock().await.handles();
+    let mut known: Option<(String, Status)> = None;
+    for (n, r) in handles {
+        let mut r = r.lock().await;
+        if id.is_some() && r.get_id() == id {
+            // keep the status we know, the one in register message is just initial status.
+            known = Some((n, r.get_status().clone()));
+            break;
+        }
+    }
+
+    resource.set_id(i);
+    let mut rs = RESOURCES.lock().await;
+    let renamed = match known {
+        Some((old, status)) => {
+            if old != name && rs.contains(&name) {
+                return Registration::Conflict;
+            }
+            resource.set_status(status);
+            rs.remove(&old);
+            rs.insert(name.clone(), Arc::new(Mutex::new(resource)));
+            Some(old)
+        },
+        None => {
+            if rs.contains(&name) {
+                warn!("resource {} from {} conflict with registered one", name, resource.get_address());
+                return Registration::Conflict;
+            }
+            rs.insert(name.clone(), Arc::new(Mutex::new(resource)));
+            None
+        },
+    };
+    drop(rs);
+
+    let token = OWNERS.lock().await.own(i, token);
+    match renamed {
+        Some(old) => {
+            if old != name {
+                HEALTH.lock().await.remove(&old);
+                info!("resource {} renamed to {}", old, name);
+            }
+            Registration::Renew(i, token)
+        },
+        None => Registration::New(i, token),
+    }
 }
 
 fn message2resource(message: String) -> BoxResult<InternetResource> {
@@ -271,8 +427,12 @@ fn message2resource(message: String) -> BoxResult<InternetResource> {
 
 pub async fn complete_intent(intent: &mut Intent) -> Result<i64, Box<dyn Error>> {
     let intent_source = intent.get_resource().unwrap();
-    let src = match INTERNET_RESOURCES.lock().await.get(intent_source) {
-        Some(resource) => resource.lock().await.get_address().clone(),
+    let resource = RESOURCES.lock().await.get(intent_source);
+    let src = match resource {
+        Some(r) => match r.lock().await.get_address() {
+            ResourceAddress::Internet(a) => a,
+            _ => return Ok(0),
+        },
         None => {
             warn!("resource have been removed");
             return Ok(0);
@@ -286,11 +446,9 @@ pub async fn complete_intent(intent: &mut Intent) -> Result<i64, Box<dyn Error>>
 }
 
 async fn find_resource_by_addr(addr: &SocketAddr) -> Option<String> {
-    for (_, i) in INTERNET_RESOURCES.lock().await.iter() {
-        let i_r = i.lock().await;
-        if i_r.get_address() == addr {
-            return Some(i_r.get_name().to_string());
-        } else {
+    for (name, a) in heartbeat_resources().await {
+        if a == *addr {
+            return Some(name);
         }
     }
     // "Intent input".to_string()
@@ -299,16 +457,20 @@ async fn find_resource_by_addr(addr: &SocketAddr) -> Option<String> {
 }
 
 
-async fn mark_complete(sub_id: i64) ->BoxResult<()> {
+pub async fn mark_complete(sub_id: i64) ->BoxResult<()> {
     // let mut id = 0;
     // let mut name: &str = "";
     let mut i_q = INTENT_QUEUE.lock().await;
     let mut c = false;
     for i in i_q.iter_mut() {
     // for i in INTENT_QUEUE.lock().await.iter_mut() {
+        let i_id = i.get_id();
         for ii in i.iter_sub_intent() {
             if ii.get_id() != sub_id || ii.is_complete() { continue; }
             ii.complete();
+            HEALTH.lock().await.complete(sub_id, Outcome::Success);
+            let cost = ii.get_cost().unwrap_or_default();
+            emit_with_cost(EventKind::SubComplete, i_id, Some(sub_id), ii.get_selected_resource().map(|r| r.as_str()), ii.get_description(), cost);
             // name = ii.get_selected_resource().unwrap();
             c = true;
         }
@@ -317,6 +479,7 @@ async fn mark_complete(sub_id: i64) ->BoxResult<()> {
             if i.is_complete() {
                 complete_intent(i).await.unwrap();
                 let id = i.get_id();
+                emit_with_cost(EventKind::Complete, id, None, i.get_resource().map(|r| r.as_str()), i.get_description(), i.get_cost());
                 i_q.retain(|i| i.get_id() != id);
                 info!("Handler Over");
             }
@@ -330,12 +493,10 @@ async fn mark_complete(sub_id: i64) ->BoxResult<()> {
 }
 
 async fn send_heartbeat() -> BoxResult<()> {
-    for (name, resource) in INTERNET_RESOURCES.lock().await.iter() {
-        let r = resource.lock().await;
-        let address = r.get_address();
+    for (name, address) in heartbeat_resources().await {
         let m = Message::new(MessageType::Heartbeat, "".to_string(), None);
         let m_json = serde_json::to_string(&m)?;
-        match get_udp!().try_send_to(&m_json.as_bytes(), *address) {
+        match get_udp!().try_send_to(&m_json.as_bytes(), address) {
             Ok(_) => {
                 info!("Heartbeat sent to {}", address)
             },
@@ -356,7 +517,7 @@ async fn send_heartbeat() -> BoxResult<()> {
             if retry == 0 {
                 warn!("resource of {} disappear", address);
                 warn!("remove resource {} ", name);
-                INTERNET_RESOURCES.lock().await.remove(name);
+                remove_resource_by_name(&name).await;
                 break;
             }
             // sleep(time::Duration::from_secs(1));
@@ -365,7 +526,9 @@ async fn send_heartbeat() -> BoxResult<()> {
     Ok(())
 }
 
-async fn interpret_intent(interpreter: &Interpreter, i: &str) -> String {
+// translate the intent into device command, the error of script interpreter tell whether
+// the resource reject the intent or the interpreter fail.
+pub async fn interpret_intent(interpreter: &Interpreter, capability: &Capability, i: &str) -> BoxResult<String> {
     // match 
     let command = match interpreter {
         Interpreter::LLM(s) => {
@@ -391,44 +554,73 @@ The target commands are '{s}'.");
             let u_prompt = format!("intent: {i}");
             llmq::prompt(&s_prompt, &u_prompt).await
         },
-        Interpreter::PathBuf(_p) => {
-            "".to_string()
+        Interpreter::PathBuf(p) => {
+            run_script(p, capability, i).await?
+        },
+        Interpreter::Classification(c) => {
+            command_selector(c, i)?
         },
         _ => {
             "".to_string()
         },
     };
-    command
+    Ok(command)
 }
 
-pub async fn send_message_internet(r: tokio::sync::MutexGuard<'_, InternetResource>, i: &str, m_type: MessageType, id: Option<i64>) -> BoxResult<()> {
-    // info!("message start");
-    let addr = r.get_address();
-    let message = if r.is_interpreter_none() {
-        let m = Message::new(m_type, i.to_string(), id);
-        serde_json::to_string(&m)?
-    } else {
-        format!("{}:{}",interpret_intent(r.get_interpreter(), i).await, id.unwrap()) 
-    };
-    let data: Vec<u8> = message.as_bytes().to_vec();
-    get_udp!().send_to(&data, addr).await?;
-    // info!("message send {addr}");
-    Ok(())
+impl Transport for InternetResource {
+    fn send<'a>(&'a self, body: &'a str, m_type: MessageType, id: Option<i64>) -> BoxFuture<'a, BoxResult<()>> {
+        Box::pin(async move {
+            // info!("message start");
+            let addr = self.get_address();
+            let message = self.encode(body, m_type, id).await?;
+            let data: Vec<u8> = message.as_bytes().to_vec();
+            get_udp!().send_to(&data, addr).await?;
+            // info!("message send {addr}");
+            Ok(())
+        })
+    }
+
+    fn send_raw<'a>(&'a self, command: &'a str) -> BoxFuture<'a, BoxResult<()>> {
+        Box::pin(async move {
+            get_udp!().send_to(command.as_bytes(), self.get_address()).await?;
+            Ok(())
+        })
+    }
+
+    fn liveness(&self) -> Liveness {
+        Liveness::Heartbeat
+    }
+
+    fn get_id(&self) -> Option<i64> {
+        self.get_id()
+    }
+}
+
+// internet resources whose liveness is checked by heartbeat.
+async fn heartbeat_resources() -> Vec<(String, SocketAddr)> {
+    let mut resources = vec![];
+    let handles = RESOURCES.lock().await.handles();
+    for (name, r) in handles {
+        let r = r.lock().await;
+        if let (Liveness::Heartbeat, ResourceAddress::Internet(addr)) = (r.liveness(), r.get_address()) {
+            resources.push((name, addr));
+        }
+    }
+    resources
 }
 
 async fn query_status() -> BoxResult<()> {
     let m = Message::new(MessageType::Status, "".to_string(), None);
     let m_json = serde_json::to_string(&m)?;
     let buf = &m_json.as_bytes().to_vec();
-    for s in INTERNET_RESOURCES.lock().await.values() {
-        let addr = s.lock().await.get_address().clone();
+    for (_, addr) in heartbeat_resources().await {
         SOCKET.lock().await.as_ref().unwrap().send_to(buf, addr).await?;
     }
     Ok(())
 }
 
 // async fn check_status() {
-//     for s in INTERNET_RESOURCES.lock().await.values() {
+//     for s in RESOURCES.lock().await.handles() {
 //         let mut s = s.lock().await;
 //         let status = s.get_status();
 //         check_position(&status.get_position());
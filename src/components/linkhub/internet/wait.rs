@@ -1,7 +1,7 @@
 
 use std::{
-    str, sync::Arc, 
-    thread::sleep, time,
+    str, thread::sleep, time,
+    sync::{atomic::{AtomicBool, Ordering}, Arc},
     net::{IpAddr, Ipv4Addr, SocketAddr}, 
 };
 use tokio::{net::UdpSocket, sync::Mutex, time::interval};
@@ -17,12 +17,16 @@ use crate::{
     }, 
     components::linkhub::{
         internet::{
+            owner::{Credential, CREDENTIALS},
             resource::InternetResource, 
-            seek::TAPE_ADDRESS
         }, 
         waiter::{HEART, ITAPE, TAPE, TAPE_INTENT_QUEUEUE}
     }, 
-    core::inxt::intent::{execute, handler}
+    core::inxt::intent::{execute, handler, simulate_execute},
+    tools::{
+        discovery::{browse_tapes, choose_tape, find_register_server, REGISTER},
+        simulator::{Behavior, Decision, DRIFT_D},
+    },
 };
 
 
@@ -33,8 +37,30 @@ const PORT: u16 = 8080;
 const END: bool = true;
 const COMMAND:&str = "";
 
+// what the waiter keeps while it serves, shared by its loop and the tasks handling messages.
+#[derive(Clone)]
+struct Waiter {
+    socket: Arc<UdpSocket>,
+    tape_i: Arc<Mutex<Option<SocketAddr>>>,
+    tape_o: Arc<Mutex<Option<SocketAddr>>>,
+    status: Arc<Mutex<Status>>,
+    name: String,
+    r_json: String,
+    behavior: Option<Arc<Behavior>>,
+    // whether a task is finding TAPE now.
+    finding: Arc<AtomicBool>,
+}
+
+pub async fn wait(name: String, desc: String, port: u16) -> BoxResult<()> {
+    serve(name, desc, port, None).await
+}
 
-pub async fn wait(mut name: String, mut desc: String, mut port: u16) -> BoxResult<()> {
+// simulated resource deal with intents and its connection as the behavior says.
+pub async fn simulate(name: String, desc: String, port: u16, behavior: Behavior) -> BoxResult<()> {
+    serve(name, desc, port, Some(Arc::new(behavior))).await
+}
+
+async fn serve(mut name: String, mut desc: String, mut port: u16, behavior: Option<Arc<Behavior>>) -> BoxResult<()> {
     if name.is_empty() {
         name = NAME.to_string();
     }
@@ -48,27 +74,49 @@ pub async fn wait(mut name: String, mut desc: String, mut port: u16) -> BoxResul
     let (
         socket, 
         input_socket,
-        m_json
+        r_json
     ) = init(name.clone(), desc, port).await?;
 
-    let tape_i: Arc<Mutex<Option<SocketAddr>>> = Arc::new(Mutex::new(None));
-    let tape_o: Arc<Mutex<Option<SocketAddr>>> = Arc::new(Mutex::new(None));
-    let socket = Arc::new(socket);
-    let status = Arc::new(Mutex::new(Status::new(true, (0.0, 0.0, 0.0), time::Duration::from_secs(0))));
+    let w = Waiter {
+        socket: Arc::new(socket),
+        tape_i: Arc::new(Mutex::new(None)),
+        tape_o: Arc::new(Mutex::new(None)),
+        status: Arc::new(Mutex::new(Status::new(true, (0.0, 0.0, 0.0), time::Duration::from_secs(0)))),
+        name,
+        r_json,
+        behavior,
+        finding: Arc::new(AtomicBool::new(false)),
+    };
 
-    let v_position: ((f32, f32), (f32, f32), (f32, f32)) = ((0.0, 0.0), (0.0, 0.0), (0.0, 0.0));
-    find_register(&socket, false, v_position).await; 
+    w.spawn_find_register();
     
     let mut register = interval(time::Duration::from_secs(10));
     let mut check_register = interval(time::Duration::from_secs(30)); // check register must be slower than heart beat
     let _ = check_register.tick().await;
+    let mut drift = interval(DRIFT_D);
+    let mut offline = false;
     let mut input_buf = [0; 1024];
     loop {
         let mut buf = [0; 1024];
         // waiting for intent
         tokio::select! {
+            _ = drift.tick(), if w.behavior.is_some() => {
+                let b = w.behavior.as_ref().unwrap();
+                b.drift(&mut *w.status.lock().await);
+                let now_offline = b.is_offline();
+                if now_offline != offline {
+                    info!("{} is {}", w.name, if now_offline { "offline" } else { "online" });
+                }
+                if offline && !now_offline {
+                    // TAPE has removed us while we are offline.
+                    *w.tape_i.lock().await = None;
+                    *w.tape_o.lock().await = None;
+                    w.spawn_find_register();
+                }
+                offline = now_offline;
+            },
             _ = register.tick(), if TAPE.lock().await.is_none() => {
-                find_register(&socket, false, v_position).await; 
+                w.spawn_find_register();
             },
             _ = check_register.tick(), if !TAPE.lock().await.is_none() => {
                 if *HEART.lock().await {
@@ -77,13 +125,13 @@ pub async fn wait(mut name: String, mut desc: String, mut port: u16) -> BoxResul
                 }
                 warn!("no heart beat, disconnect");
                 *TAPE.lock().await = ResourceType::None;
-                *tape_i.lock().await = None;
-                *tape_o.lock().await = None;
+                *w.tape_i.lock().await = None;
+                *w.tape_o.lock().await = None;
             },
             Ok((amt, _))  = input_socket.recv_from(&mut input_buf) => {
                 match str::from_utf8(&input_buf[0..amt]) {
                     Ok(m_body) => {
-                        if tape_i.lock().await.is_none() {
+                        if w.tape_i.lock().await.is_none() {
                             warn!("send to seeker please");
                             continue;
                         }
@@ -93,7 +141,7 @@ pub async fn wait(mut name: String, mut desc: String, mut port: u16) -> BoxResul
                         
                         // we only send plain text intent so that the bandwidth cost will reduce
                         let m = Message::new(MessageType::Intent, m_body.to_string(), Some(i.get_id()));
-                        match send_message(&socket, &tape_i.lock().await.unwrap(), &m).await {
+                        match send_message(&w.socket, &w.tape_i.lock().await.unwrap(), &m).await {
                             Ok(_) => {
                                 TAPE_INTENT_QUEUEUE.lock().await.push(i);
                             },
@@ -105,49 +153,98 @@ pub async fn wait(mut name: String, mut desc: String, mut port: u16) -> BoxResul
                     },
                 } 
             }
-            Ok((amt, src)) = socket.recv_from(&mut buf) => {
-                let c_tape_i = Arc::clone(&tape_i);
-                let c_tape_o = Arc::clone(&tape_o);
-                let c_socket = Arc::clone(&socket);
-                let c_status = Arc::clone(&status);
-                let c_m_json = m_json.clone();
+            Ok((amt, src)) = w.socket.recv_from(&mut buf) => {
+                // nothing is heard while offline.
+                if offline {
+                    continue;
+                }
+                let c_w = w.clone();
                 tokio::spawn(async move{
-                    let _ = message_handler(src, amt, c_tape_i, c_tape_o, &buf, c_socket, c_m_json, c_status).await;
+                    let _ = message_handler(c_w, src, amt, &buf).await;
                 });
             }
         }
     }
 }
 
-async fn find_register(socket: &UdpSocket, tape: bool, position: ((f32, f32), (f32, f32), (f32, f32))) {
-    let v4: Ipv4Addr = Ipv4Addr::new(127, 0, 0, 1);
-    let ipv4 = IpAddr::V4(v4);
-    let r = RegisterServer::new(tape, None, None, position);
+impl Waiter {
+    // browsing takes seconds, so TAPE is found in its own task and messages are not held up meanwhile.
+    // only one task finds TAPE at a time.
+    fn spawn_find_register(&self) {
+        if self.finding.swap(true, Ordering::SeqCst) {
+            return;
+        }
+        let w = self.clone();
+        tokio::spawn(async move {
+            // the tick may come before the answer of registration is handled.
+            if !TAPE.lock().await.is_none() {
+                w.finding.store(false, Ordering::SeqCst);
+                return;
+            }
+            let s = w.status.lock().await.clone();
+            find_register(&w.socket, false, &s, &w.tape_i, &w.tape_o, &w.name, &w.r_json).await;
+            w.finding.store(false, Ordering::SeqCst);
+        });
+    }
+}
+
+// find the TAPE which serve where we are, TAPEs advertised on LAN are tried first,
+// and register server is asked if none of them suit us.
+async fn find_register(
+    socket: &UdpSocket,
+    tape: bool,
+    status: &Status,
+    tape_i: &Mutex<Option<SocketAddr>>,
+    tape_o: &Mutex<Option<SocketAddr>>,
+    name: &str,
+    r_json: &str,
+) {
+    // registration is not answered yet, ask the same TAPE again, it may be found at another address
+    // and the registration from another source would conflict with this one.
+    let pending = *tape_i.lock().await;
+    if let Some(i) = pending {
+        send_register(socket, &i, name, r_json).await;
+        return;
+    }
+    let tapes = browse_tapes().await;
+    if let Some(t) = choose_tape(&tapes, status.get_position(), status.get_place()) {
+        info!("found TAPE {} on LAN", t.get_iaddr());
+        let data = serde_json::to_string(&t.to_register_server()).unwrap();
+        match connect_tape(&data, tape_i, tape_o, socket, name, r_json).await {
+            Ok(_) => return,
+            Err(e) => warn!("connect TAPE error: {}", e),
+        }
+    }
+    let r = RegisterServer::new(tape, None, None, Vec::new(), status.get_position().clone(), status.get_place().cloned());
     let r_json = serde_json::to_string(&r).unwrap();
-    let addr = SocketAddr::new(ipv4, 8000);
-    socket.send_to(&r_json.as_bytes(), addr).await.unwrap();
+    let addr = find_register_server().await;
+    if let Err(e) = socket.send_to(&r_json.as_bytes(), addr).await {
+        warn!("Failed to ask register server {}: {}, retry later", addr, e);
+    }
 } 
 
-async fn message_handler(
-    src: SocketAddr, 
-    amt: usize, 
-    tape_i: Arc<Mutex<Option<SocketAddr>>>, 
-    tape_o: Arc<Mutex<Option<SocketAddr>>>,
-    buf: &[u8],
-    socket: Arc<UdpSocket>,
-    m_json: String,
-    status: Arc<Mutex<Status>>,
+async fn connect_tape(
+    data: &str,
+    tape_i: &Mutex<Option<SocketAddr>>,
+    tape_o: &Mutex<Option<SocketAddr>>,
+    socket: &UdpSocket,
+    name: &str,
+    r_json: &str,
 ) -> BoxResult<()> {
-    let server_addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 8000);
-    if src == server_addr {
+    let tape: RegisterServer = serde_json::from_str(data)?;
+    *tape_i.lock().await = Some(tape.get_iaddr().clone());
+    *tape_o.lock().await = Some(tape.get_oaddr().clone());
+    * HEART.lock().await = true;
+    send_register(socket, &tape.get_iaddr(), name, r_json).await;
+    ITAPE.lock().await.set_address(tape.get_iaddr().clone());
+    Ok(())
+}
+
+async fn message_handler(w: Waiter, src: SocketAddr, amt: usize, buf: &[u8]) -> BoxResult<()> {
+    let Waiter { socket, tape_i, tape_o, status, name, r_json, behavior, .. } = w;
+    if src == *REGISTER.lock().await {
         let data = str::from_utf8(&buf[..amt]).unwrap();
-        let tape: RegisterServer = serde_json::from_str(data)?;
-        *tape_i.lock().await = Some(tape.get_iaddr().clone());
-        *tape_o.lock().await = Some(tape.get_oaddr().clone());
-        * HEART.lock().await = true;
-        send_register(&socket, &tape.get_iaddr(), &m_json).await;
-        ITAPE.lock().await.set_address(tape.get_iaddr().clone());
-        return Ok(());
+        return connect_tape(data, &tape_i, &tape_o, &socket, &name, &r_json).await;
     }
     if tape_o.lock().await.is_none() {
         warn!("haven't regiterd");
@@ -168,6 +265,12 @@ async fn message_handler(
             *HEART.lock().await = true;
             heart_beat_report(&socket, &tape_o.lock().await.unwrap()).await?;
         },
+        MessageType::Handover => {
+            // we moved out of region of the TAPE, register to the new one.
+            info!("handed over to {}", m.get_body());
+            *TAPE.lock().await = ResourceType::None;
+            connect_tape(&m.get_body(), &tape_i, &tape_o, &socket, &name, &r_json).await?;
+        },
         MessageType::Finish => {
             *TAPE.lock().await = ResourceType::None;
             *tape_i.lock().await = None;
@@ -175,10 +278,27 @@ async fn message_handler(
         }
         MessageType::Response => {
             match m.get_body().as_ref() {
-                "Registerd" => {
+                "Registerd" | "Reregisterd" => {
                     *TAPE.lock().await = ResourceType::Internet;
+                    // the id is kept with its token, so that we are still known after restart.
+                    if let (Some(id), Some(token)) = (m.get_id(), m.get_token()) {
+                        CREDENTIALS.lock().await.insert(name, Credential { id, token: token.to_string() });
+                    }
                     info!("register successfully: {}", str::from_utf8(&buf[..amt]).expect("Fail to convert to String"));
                 },
+                "Unauthorized" => {
+                    // the id is not ours at this TAPE, register as a new resource.
+                    warn!("id is refused, register again without it");
+                    CREDENTIALS.lock().await.remove(&name);
+                    *tape_i.lock().await = None;
+                    *tape_o.lock().await = None;
+                },
+                "Name Conflict" => {
+                    // another resource use our name, retry later in case it is a stale one of us.
+                    warn!("name conflict, register later");
+                    *tape_i.lock().await = None;
+                    *tape_o.lock().await = None;
+                },
                 "Intent Duplicate" => {
                     
                 },
@@ -223,7 +343,17 @@ async fn message_handler(
             let m_id = m.get_id().clone();
             tokio::spawn(async move {
             
-                if END {
+                if let Some(b) = behavior {
+                    let decision = b.decide();
+                    if let Decision::Execute(t) | Decision::Fail(t) = decision {
+                        let _ = simulate_execute(&c_m, c_status, t).await;
+                    }
+                    if !matches!(decision, Decision::Execute(_)) {
+                        let m = Message::new(MessageType::Reject, c_m, m_id);
+                        let _ = send_message(&c_socket, &c_tape_i.lock().await.unwrap(), &m).await;
+                        return;
+                    }
+                } else if END {
                     let _ = execute(&c_m, c_status).await.unwrap();
                 } else {
                     let i: Intent = Intent::new(c_m.clone(), IntentSource::Tape, IntentType::Intent, Some("TAPE".to_string()));
@@ -253,7 +383,8 @@ async fn init(name: String, desc: String, port: u16) -> BoxResult<(UdpSocket, Ud
     // let tape_o = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 8889);
     // let tape_i = SocketAddr::new(IpA/ddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 8888);
     let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), port);
-    let socket = UdpSocket::bind(addr).await.expect("Failed to bind to socket");
+    // TAPE may be on another host of LAN.
+    let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, port)).await.expect("Failed to bind to socket");
     let input_addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), port+20000);
     let input_socket = UdpSocket::bind(input_addr).await.expect("Failed to bind to socket");
 
@@ -261,17 +392,19 @@ async fn init(name: String, desc: String, port: u16) -> BoxResult<(UdpSocket, Ud
     let resource = InternetResource::new(name, desc, addr, status);
     let r_json = serde_json::to_string(&resource)?;
 
-    let m = Message::new(MessageType::Register, r_json, None);
-    let m_json = serde_json::to_string(&m)?;
-
-    Ok((socket, input_socket, m_json))
+    Ok((socket, input_socket, r_json))
 }
 
-async fn send_register(s: &UdpSocket, tape_i: &SocketAddr, m_json: &str) {
+// id given by TAPE is sent with its token in the register message, so that TAPE know it is still us.
+async fn send_register(s: &UdpSocket, tape_i: &SocketAddr, name: &str, r_json: &str) {
+    let credential = CREDENTIALS.lock().await.get(name).cloned();
+    let m = Message::new(MessageType::Register, r_json.to_string(), credential.as_ref().map(|c| c.id))
+        .with_token(credential.map(|c| c.token));
+    let m_json = serde_json::to_string(&m).unwrap();
     match s.send_to(&m_json.as_bytes().to_vec(), tape_i).await {
         Ok(_) => (),
         Err(e) => {
-            warn!("Failed to register to {}: {}, retry later", TAPE_ADDRESS, e);
+            warn!("Failed to register to {}: {}, retry later", tape_i, e);
         }
     }
     sleep(time::Duration::from_micros(100));
@@ -321,7 +454,7 @@ async fn send_message(socket: &UdpSocket, tape_i: &SocketAddr, m: &Message) -> B
             // info!("send message successfully: {}, {}", tape_i.port(), m_json);
         },
         Err(e) => {
-            warn!("Failed send to {}: {}, retry later", TAPE_ADDRESS, e);
+            warn!("Failed send to {}: {}, retry later", tape_i, e);
             return Err(Box::new(e));
         }
     }
@@ -0,0 +1,177 @@
+// bridge devices which already speak MQTT, so that they can be used as resources of TAPE.
+// the bridge connect to a broker and use such topics, <name> is the name of resource:
+// tape/announce          device -> TAPE, register with `{"name": .., "description": .., "commands": .., "capability": ..}`,
+//                        `commands` is optional and will be given to the LLM interpreter,
+//                        `capability` is the optional manifest of declared actions.
+// tape/<name>/command    TAPE -> device, sub-intent as `Message` json, or `command:id` for resource with interpreter.
+// tape/<name>/status     device -> TAPE, `Status` json, or `offline` to unregister.
+// tape/<name>/result     device -> TAPE, `Message` json of Response or Reject with id of the sub-intent.
+
+use std::{str, sync::Arc, time::Duration};
+use futures::future::BoxFuture;
+use lazy_static::lazy_static;
+use log::{info, warn};
+use rumqttc::{AsyncClient, Event, MqttOptions, Packet, QoS};
+use serde::Deserialize;
+use tokio::{sync::Mutex, time::sleep};
+
+use crate::{
+    base::{
+        capability::Capability,
+        errort::BoxResult,
+        message::{Message, MessageType},
+        resource::{Interpreter, Resource, ResourceAddress, Status},
+    },
+    components::linkhub::{
+        internet::seek::{mark_complete, reroute_rejected},
+        mqtt::resource::MqttResource,
+        registry::{Liveness, Transport, RESOURCES},
+        seeker::{fresh_resource_status, remove_resource_by_name},
+    },
+};
+
+pub const MQTT_BROKER: (&str, u16) = ("127.0.0.1", 1883);
+const CLIENT_ID: &str = "tapeos";
+const TOPIC_PREFIX: &str = "tape";
+const RECONNECT_D: Duration = Duration::from_secs(5);
+
+lazy_static! {
+    pub static ref MQTT_CLIENT: Mutex<Option<AsyncClient>> = Mutex::new(None);
+}
+
+#[derive(Deserialize)]
+struct Announce {
+    name: String,
+    description: String,
+    commands: Option<String>,
+    #[serde(default)]
+    capability: Capability,
+}
+
+pub async fn seek() -> BoxResult<()> {
+    seek_broker(MQTT_BROKER.0, MQTT_BROKER.1).await
+}
+
+// seek resources through the broker on host:port, such as a local mosquitto.
+pub async fn seek_broker(host: &str, port: u16) -> BoxResult<()> {
+    let mut options = MqttOptions::new(CLIENT_ID, host, port);
+    options.set_keep_alive(Duration::from_secs(20));
+    let (client, mut eventloop) = AsyncClient::new(options, 64);
+    MQTT_CLIENT.lock().await.replace(client.clone());
+
+    loop {
+        match eventloop.poll().await {
+            Ok(Event::Incoming(Packet::ConnAck(_))) => {
+                // subscriptions are lost when reconnect with a clean session.
+                info!("connected to broker {}:{}", host, port);
+                client.subscribe(format!("{TOPIC_PREFIX}/announce"), QoS::AtLeastOnce).await?;
+                client.subscribe(format!("{TOPIC_PREFIX}/+/status"), QoS::AtLeastOnce).await?;
+                client.subscribe(format!("{TOPIC_PREFIX}/+/result"), QoS::AtLeastOnce).await?;
+            },
+            Ok(Event::Incoming(Packet::Publish(p))) => {
+                tokio::spawn(async move {
+                    if let Err(e) = message_handler(&p.topic, &p.payload).await {
+                        warn!("mqtt message on {} error: {}", p.topic, e);
+                    }
+                });
+            },
+            Ok(_) => (),
+            Err(e) => {
+                warn!("mqtt connection error: {}, retry later", e);
+                sleep(RECONNECT_D).await;
+            },
+        }
+    }
+}
+
+async fn message_handler(topic: &str, payload: &[u8]) -> BoxResult<()> {
+    let payload = str::from_utf8(payload)?;
+    let topic = match topic.strip_prefix(TOPIC_PREFIX).and_then(|t| t.strip_prefix('/')) {
+        Some(t) => t,
+        None => return Ok(()),
+    };
+    if topic == "announce" {
+        let a: Announce = serde_json::from_str(payload)?;
+        store_resource(a).await;
+        return Ok(());
+    }
+    let (name, kind) = match topic.split_once('/') {
+        Some(p) => p,
+        None => return Ok(()),
+    };
+    match kind {
+        "status" => {
+            if payload == "offline" {
+                info!("mqtt resource {} offline", name);
+                remove_resource_by_name(name).await;
+                return Ok(());
+            }
+            let s: Status = serde_json::from_str(payload)?;
+            fresh_resource_status(name, s).await;
+        },
+        "result" => {
+            let m: Message = serde_json::from_str(payload)?;
+            let id = m.get_id().ok_or("result without id")?;
+            match m.get_type() {
+                MessageType::Response => mark_complete(id).await?,
+                MessageType::Reject => reroute_rejected(id).await?,
+                _ => warn!("do not support such result from {}", name),
+            }
+        },
+        _ => (),
+    }
+    Ok(())
+}
+
+// a device announce again when it changes, then the registered resource is updated.
+async fn store_resource(a: Announce) {
+    let topic = format!("{TOPIC_PREFIX}/{}/command", a.name);
+    let interpreter = match a.commands {
+        Some(c) => Interpreter::LLM(c),
+        None => Interpreter::None,
+    };
+    let mut rs = RESOURCES.lock().await;
+    let handle = match rs.get(&a.name) {
+        Some(h) => h,
+        None => {
+            let mut r = MqttResource::new(a.name.clone(), a.description, topic);
+            r.set_interpreter(interpreter);
+            r.set_capability(a.capability);
+            info!("mqtt resource {} registered", a.name);
+            rs.insert(a.name, Arc::new(Mutex::new(r)));
+            return;
+        },
+    };
+    drop(rs);
+
+    let mut r = handle.lock().await;
+    if r.get_address() != ResourceAddress::Mqtt(topic) {
+        warn!("mqtt resource {} conflicts with a registered resource", a.name);
+        return;
+    }
+    r.set_description(a.description);
+    r.set_interpreter(interpreter);
+    r.set_capability(a.capability);
+    info!("mqtt resource {} updated", a.name);
+}
+
+impl Transport for MqttResource {
+    fn send<'a>(&'a self, body: &'a str, m_type: MessageType, id: Option<i64>) -> BoxFuture<'a, BoxResult<()>> {
+        Box::pin(async move {
+            let message = self.encode(body, m_type, id).await?;
+            self.send_raw(&message).await
+        })
+    }
+
+    fn send_raw<'a>(&'a self, command: &'a str) -> BoxFuture<'a, BoxResult<()>> {
+        Box::pin(async move {
+            let client = MQTT_CLIENT.lock().await.clone().ok_or("mqtt bridge is not started")?;
+            client.publish(self.get_topic(), QoS::AtLeastOnce, false, command.as_bytes().to_vec()).await?;
+            Ok(())
+        })
+    }
+
+    fn liveness(&self) -> Liveness {
+        Liveness::Connection
+    }
+}
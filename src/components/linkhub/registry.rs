@@ -0,0 +1,138 @@
+// in this file, we will keep all resources no matter which transport they come from.
+// every transport implement `Transport` for its resource, so that the seeker and the
+// router can use resources without knowing how to talk with them.
+// adding a new transport only need to implement `Transport` and insert its resources here.
+
+use std::{collections::HashMap, sync::Arc};
+use futures::future::BoxFuture;
+use lazy_static::lazy_static;
+use tokio::sync::Mutex;
+
+use crate::{
+    base::{decision::Explanation, errort::BoxResult, message::{Message, MessageType}, resource::Resource},
+    components::linkhub::internet::seek::interpret_intent,
+};
+
+pub type Handle = Arc<Mutex<dyn Transport>>;
+
+lazy_static! {
+    pub static ref RESOURCES: Mutex<ResourceRegistry> = Mutex::new(ResourceRegistry::new());
+}
+
+// how do we know the resource is still alive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Liveness {
+    // TAPE send heartbeat and remove the resource which do not answer.
+    Heartbeat,
+    // transport tell us when the resource is gone, like disconnect of bluetooth,
+    // `offline` status of MQTT or cancelled observation of CoAP.
+    Connection,
+}
+
+// address of resource is given by `Resource::get_address`.
+pub trait Transport: Resource {
+    // send message of m_type to the resource, body is interpreted if the resource need.
+    fn send<'a>(&'a self, body: &'a str, m_type: MessageType, id: Option<i64>) -> BoxFuture<'a, BoxResult<()>>;
+    // send command as it is, without wrapping it into message.
+    fn send_raw<'a>(&'a self, command: &'a str) -> BoxFuture<'a, BoxResult<()>>;
+    fn liveness(&self) -> Liveness;
+    // what `send` put on the wire. only sub-intents with id are interpreted for resources without
+    // declared capability, or with script; action call and message without id, like rejection
+    // of an intent, are wrapped into message as they are, so the device never run them.
+    fn encode<'a>(&'a self, body: &'a str, m_type: MessageType, id: Option<i64>) -> BoxFuture<'a, BoxResult<String>> {
+        Box::pin(async move {
+            let interpreted = !self.is_interpreter_none() && (self.get_capability().is_empty() || self.get_interpreter().is_script());
+            match id {
+                Some(id) if interpreted => {
+                    Ok(format!("{}:{}", interpret_intent(self.get_interpreter(), self.get_capability(), body).await?, id))
+                },
+                _ => Ok(serde_json::to_string(&Message::new(m_type, body.to_string(), id))?),
+            }
+        })
+    }
+    // tell the resource its intent is rejected, with the explanation if rules reject it.
+    fn reject<'a>(&'a self, body: &'a str, explanation: Option<&'a Explanation>) -> BoxFuture<'a, BoxResult<()>> {
+        Box::pin(async move {
+            match explanation {
+                Some(x) => {
+                    let m = Message::new(MessageType::Reject, body.to_string(), None).with_explanation(Some(x.clone()));
+                    self.send_raw(&serde_json::to_string(&m)?).await
+                },
+                None => self.send(body, MessageType::Reject, None).await,
+            }
+        })
+    }
+    // read what the resource has for TAPE, only for transports which poll their resources, like bluetooth.
+    fn receive(&self) -> BoxFuture<'_, BoxResult<Option<String>>> {
+        Box::pin(async { Ok(None) })
+    }
+    // stable id of resource, if the transport give one.
+    fn get_id(&self) -> Option<i64> {
+        None
+    }
+}
+
+pub struct ResourceRegistry {
+    resources: HashMap<String, Handle>,
+}
+
+impl ResourceRegistry {
+    fn new() -> Self {
+        Self { resources: HashMap::new() }
+    }
+
+    pub fn insert(&mut self, name: String, resource: Handle) {
+        self.resources.insert(name, resource);
+    }
+
+    pub fn remove(&mut self, name: &str) -> Option<Handle> {
+        self.resources.remove(name)
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.resources.contains_key(name)
+    }
+
+    // handle is cloned so that the registry need not be locked while using the resource.
+    pub fn get(&self, name: &str) -> Option<Handle> {
+        self.resources.get(name).cloned()
+    }
+
+    pub fn handles(&self) -> Vec<(String, Handle)> {
+        self.resources.iter().map(|(n, r)| (n.clone(), Arc::clone(r))).collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.resources.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.resources.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::{base::resource::Interpreter, components::linkhub::mqtt::resource::MqttResource};
+
+    #[tokio::test]
+    async fn messages_without_id_are_not_interpreted() {
+        let cases = [
+            (Interpreter::None, MessageType::Intent, Some(7)),
+            (Interpreter::LLM("on:turn on;off:turn off".into()), MessageType::Reject, None),
+            (Interpreter::PathBuf(PathBuf::from("/nonexistent")), MessageType::Reject, None),
+            (Interpreter::Classification(vec!["on".into(), "off".into()]), MessageType::Reject, None),
+        ];
+        for (interpreter, m_type, id) in cases {
+            let mut r = MqttResource::new("lamp".into(), "lamp".into(), "tape/lamp/command".into());
+            r.set_interpreter(interpreter);
+            let encoded = r.encode("turn on the lamp", m_type, id).await.unwrap();
+            let m: Message = serde_json::from_str(&encoded).unwrap();
+            assert_eq!(m.get_body(), "turn on the lamp");
+            assert_eq!(m.get_id(), id);
+        }
+    }
+}
@@ -16,21 +16,25 @@ use std::{
     time::Duration
 };
 use lazy_static::lazy_static;
-use log::info;
+use log::warn;
 use tokio::sync::Mutex;
 
 use crate::{
     base::{
+        acl::{Principal, ACL},
+        capability::Capability,
+        decision::Explanation,
         errort::BoxResult, 
+        health::HEALTH,
+        telemetry::TELEMETRY,
         intent::Intent, 
-        resource::{Status, ResourceType, Resource},
-        message::{Message, MessageType}, 
+        resource::{is_in_area, Place, Status, ResourceType},
+        message::MessageType, 
     }, 
     components::linkhub::{
-        wifi,
+        wifi, bluetooth, internet, mqtt, coap,
         waiter::{BTAPE, ITAPE, TAPE},
-        bluetooth::{self, resource::BluetoothResource}, 
-        internet::{self, resource::InternetResource, seek::send_message_internet}, 
+        registry::{Handle, Transport, RESOURCES},
     }
 };
 
@@ -39,19 +43,17 @@ enum SeekMethod {
     Bluetooth,
     Wifi,
     Internet,
+    Mqtt,
+    Coap,
     RFID,
     NFC,
 }
 
-type ResourcePool<T> = Arc<Mutex<HashMap<String, Arc<Mutex<T>>>>>;
 type Queue<T> = Mutex<Vec<T>>;
 
 const SEEK_METHOD: SeekMethod = SeekMethod::Internet;
 lazy_static! {
-    // we use these resource seperately for different seeker.
-    pub static ref INTERNET_RESOURCES: ResourcePool<InternetResource>= Arc::new(Mutex::new(HashMap::new()));
-    pub static ref BLUETOOTH_RESOURCES: ResourcePool<BluetoothResource> = Arc::new(Mutex::new(HashMap::new()));
-    // ...
+    // resources of all seekers are kept in `registry::RESOURCES`.
     pub static ref INTENT_QUEUE: Queue<Intent> = Mutex::new(Vec::new());
     pub static ref RESPONSE_QUEUE: Queue<HashMap<String, String>> = Mutex::new(Vec::new());
     pub static ref SEEK_SEND: Mutex<Option<Sender<String>>> = Mutex::new(None);
@@ -69,254 +71,170 @@ pub async fn seek() -> BoxResult<()> {
         SeekMethod::Bluetooth => bluetooth::seek::seek(),
         SeekMethod::Wifi => wifi::seek::seek(),
         SeekMethod::Internet => internet::seek::seek().await,
+        SeekMethod::Mqtt => mqtt::seek::seek().await,
+        SeekMethod::Coap => coap::seek::seek().await,
         _ => {
             return Err("Unsupported seek method".into());
         }
     }
 }
 
-pub async fn get_all_resource_info() -> String {
+// resources whose circuit is open or out of the area will not be given.
+// only resources in the area and permitted to the principals are listed.
+pub async fn get_all_resource_info(area: Option<&Place>, principals: &[Principal]) -> String {
+    let acl = ACL.lock().await;
     let mut resources_info = String::new();
-    for (_, resource) in BLUETOOTH_RESOURCES.lock().await.iter() {
-        let r = resource.lock().await;
-        resources_info += format!("{}/{}/{};", r.get_name(), r.get_description(), r.display_status()).as_str();
-    }
-    for (_, resource) in INTERNET_RESOURCES.lock().await.iter() {
-        let r = resource.lock().await;
-        resources_info += format!("{}/{}/{};", r.get_name(), r.get_description(), r.display_status()).as_str();
+    let handles = RESOURCES.lock().await.handles();
+    for (_, resource) in handles {
+        let mut r = resource.lock().await;
+        if !HEALTH.lock().await.is_available(r.get_name()) || !is_in_area(r.get_status().get_place(), area)
+            || acl.check(principals, r.get_name(), None).is_err() {
+            continue;
+        }
+        let status = r.get_status().clone();
+        let fields = display_fields(r.get_capability(), &status);
+        resources_info += format!("{}/{}{}{}/{};", r.get_name(), r.get_description(), display_capability(r.get_capability()), fields, r.display_status()).as_str();
     }
 
     resources_info
 }
 
+fn display_capability(c: &Capability) -> String {
+    if c.is_empty() {
+        return "".to_string();
+    }
+    format!(" Actions: {}", c)
+}
+
+// custom status fields with their units, like ` Status: temperature=42 °C, mode="eco"`.
+fn display_fields(c: &Capability, s: &Status) -> String {
+    if s.get_fields().is_empty() {
+        return "".to_string();
+    }
+    let fields = s.get_fields().iter().map(|(k, v)| {
+        let unit = c.get_status_field(k).and_then(|p| p.get_unit()).map(|u| format!(" {u}")).unwrap_or_default();
+        format!("{}={}{}", k, v, unit)
+    }).collect::<Vec<String>>();
+    format!(" Status: {}", fields.join(", "))
+}
+
+async fn get_resource(name: &str) -> Option<Handle> {
+    RESOURCES.lock().await.get(name)
+}
+
+// empty capability is returned if resource does not exist or declare nothing.
+pub async fn get_resource_capability(name: &str) -> Capability {
+    match get_resource(name).await {
+        Some(r) => r.lock().await.get_capability().clone(),
+        None => Capability::default(),
+    }
+}
+
+pub async fn get_resource_status(name: &str) -> Option<Status> {
+    let r = get_resource(name).await?;
+    let status = r.lock().await.get_status().clone();
+    Some(status)
+}
+
 pub async fn get_resource_description(name: &str) -> String {
-    match INTERNET_RESOURCES.lock().await.get(name) {
-        Some(resource) => {
-            return resource.lock().await.get_description().to_string();
-        },
-        None => (),
+    match get_resource(name).await {
+        Some(r) => r.lock().await.get_description().to_string(),
+        None => "".to_string(),
     }
-    match BLUETOOTH_RESOURCES.lock().await.get(name) {
-        Some(resource) => {
-            return resource.lock().await.get_description().to_string();
-        },
-        None => (),
-    } 
-    "".to_string()
 }
 
-pub async fn fresh_resource_status(name: &str, s: Status) -> bool {
-    match INTERNET_RESOURCES.lock().await.get(name) {
+pub async fn fresh_resource_status(name: &str, mut s: Status) -> bool {
+    match get_resource(name).await {
         Some(r) => {
-            r.lock().await.set_status(s.clone());
+            let mut r = r.lock().await;
+            for p in r.get_capability().check_status(&mut s) {
+                warn!("status of {}: {}", name, p);
+            }
+            TELEMETRY.lock().await.record(name, &s);
+            r.set_status(s);
+            true
         },
-        None => (),
+        None => false,
     }
-    match BLUETOOTH_RESOURCES.lock().await.get(name) {
-        Some(r) => {
-            r.lock().await.set_status(s.clone());
-        },
-        None => (),
-    } 
-    false
 }
 
 pub async fn remove_resource_by_name(name: &str){
-    INTERNET_RESOURCES.lock().await.remove(name);
-    BLUETOOTH_RESOURCES.lock().await.remove(name);
+    RESOURCES.lock().await.remove(name);
+    HEALTH.lock().await.remove(name);
+    TELEMETRY.lock().await.mark_removed(name);
 }
 
 // if op is true add one dealing
 pub async fn change_resource_dealing(name: &str, op: bool) {
-
-    match INTERNET_RESOURCES.lock().await.get(name) {
-        Some(r) => {
-            r.lock().await.get_status().change_dealing(op);
-        },
-        None => (),
+    if let Some(r) = get_resource(name).await {
+        r.lock().await.get_status().change_dealing(op);
     }
-    match BLUETOOTH_RESOURCES.lock().await.get(name) {
-        Some(r) => {
-            r.lock().await.get_status().change_dealing(op);
-        },
-        None => (),
-    } 
 }
 
 pub async fn add_resource_total_busy(name: &str, d: Duration) -> Duration {
-
-    match INTERNET_RESOURCES.lock().await.get(name) {
-        Some(r) => {
-            return r.lock().await.get_status().add_total_busy(d);
-        },
-        None => (),
+    match get_resource(name).await {
+        Some(r) => r.lock().await.get_status().add_total_busy(d),
+        None => Duration::from_secs(0),
     }
-    match BLUETOOTH_RESOURCES.lock().await.get(name) {
-        Some(r) => {
-            return r.lock().await.get_status().add_total_busy(d);
-        },
-        None => (),
-    } 
-    Duration::from_secs(0)
 }
 
 pub async fn get_resource_average_busy(name: &str) -> Duration {
-
-    match INTERNET_RESOURCES.lock().await.get(name) {
-        Some(r) => {
-            return r.lock().await.get_status().get_average_time();
-        },
-        None => (),
+    match get_resource(name).await {
+        Some(r) => r.lock().await.get_status().get_average_time(),
+        None => Duration::from_secs(0),
     }
-    match BLUETOOTH_RESOURCES.lock().await.get(name) {
-        Some(r) => {
-            return r.lock().await.get_status().get_average_time();
-        },
-        None => (),
-    } 
-    Duration::from_secs(0)
 }
 
 pub async fn calculate_base_dealing(name: &str) -> u64 {
-
-    match INTERNET_RESOURCES.lock().await.get(name) {
+    match get_resource(name).await {
         Some(r) => {
-
-            let dealing = r.lock().await.get_status().get_dealing() as u64;
-            let average_time = r.lock().await.get_status().get_average_time().as_secs();
-            let busy_time = r.lock().await.get_status().get_busy_time().as_secs();
-            return dealing*average_time + busy_time;
+            let mut r = r.lock().await;
+            let dealing = r.get_status().get_dealing() as u64;
+            let average_time = r.get_status().get_average_time().as_secs();
+            let busy_time = r.get_status().get_busy_time().as_secs();
+            dealing*average_time + busy_time
         },
-        None => (),
+        None => u64::MAX,
     }
-    match BLUETOOTH_RESOURCES.lock().await.get(name) {
-        Some(r) => {
-            let dealing = r.lock().await.get_status().get_dealing() as u64;
-            let average_time = r.lock().await.get_status().get_average_time().as_secs();
-            let busy_time = r.lock().await.get_status().get_busy_time().as_secs();
-            return dealing*average_time + busy_time;
-        },
-        None => (),
-    } 
-
-    u64::MAX
 }
 
 pub async fn get_resource_status_str(name: &str) -> String {
-    match INTERNET_RESOURCES.lock().await.get(name) {
-        Some(resource) => {
-            return resource.lock().await.display_status().to_string();
-        },
-        None => (),
+    match get_resource(name).await {
+        Some(r) => r.lock().await.display_status(),
+        None => "".to_string(),
     }
-    match BLUETOOTH_RESOURCES.lock().await.get(name) {
-        Some(resource) => {
-            return resource.lock().await.display_status().to_string();
-        },
-        None => (),
-    } 
-    "".to_string()
-}
-
-async fn send_message_bluetooth(r: Arc<Mutex<BluetoothResource>>, i: &str, i_type: MessageType, id: Option<i64>) -> BoxResult<()> {
-    let r = r.lock().await;
-    let char = r.get_char().as_ref().unwrap();
-    let reject = if r.is_interpreter_none() {
-        let m = Message::new(i_type, i.to_string(), id);
-        serde_json::to_string(&m)?
-    } else {
-        // TODO
-        let id = if id.is_none() {""} else {&(id.unwrap().to_string() + ":")};
-        i_type.to_string() + ":" + id + i
-    };
-    let data: Vec<u8> = reject.as_bytes().to_vec();
-    char.write(&data).await?;
-    info!("message send");
-    Ok(())
 }
 
-pub async fn reject_intent(resource_name: String, intent: &str) -> BoxResult<()> {
-    let r_m = INTERNET_RESOURCES.lock().await;
-    let r = r_m.get(&resource_name);
-    if r.is_some() {
-        match send_message_internet(r.unwrap().lock().await, intent, MessageType::Reject, None).await {
-            Ok(_) => (),
-            Err(e) => return Err(e),
-        }
+// send message to the TAPE we are connected to.
+async fn send_tape(intent: &str, m_type: MessageType, id: Option<i64>) -> BoxResult<()> {
+    match TAPE.lock().await.copy() {
+        ResourceType::Bluetooth => {
+            let r = Arc::clone(BTAPE.lock().await.as_ref().unwrap());
+            let r = r.lock().await;
+            r.send(intent, m_type, id).await
+        },
+        // TODO: may error here.
+        ResourceType::Internet => ITAPE.lock().await.send(intent, m_type, id).await,
+        _ => Ok(()),
     }
-    let _ = r;
+}
 
-    let r_m = BLUETOOTH_RESOURCES.lock().await;
-    let r = r_m.get(&resource_name);
-    if r.is_some() {
-        match send_message_bluetooth(Arc::clone(r.unwrap()), intent, MessageType::Reject, None).await {
-            Ok(_) => (),
-            Err(e) => return Err(e),
-        }
+pub async fn reject_intent(resource_name: String, intent: &str, explanation: Option<&Explanation>) -> BoxResult<()> {
+    if let Some(r) = get_resource(&resource_name).await {
+        r.lock().await.reject(intent, explanation).await?;
     }
-    let _ = r;
-    
     if resource_name == "TAPE" {
-        match TAPE.lock().await.copy() {
-            ResourceType::Bluetooth => {
-                match send_message_bluetooth(Arc::clone(BTAPE.lock().await.as_ref().unwrap()), intent, MessageType::Reject, None).await {
-                    Ok(()) => (),
-                    Err(e) => return Err(e),
-                }
-            },
-            ResourceType::Internet => {
-                match send_message_internet(ITAPE.lock().await, intent, MessageType::Reject, None).await {
-                    Ok(()) => (),
-                    Err(e) => return Err(e),
-                }
-            },
-            _ => (),
-        }
+        send_tape(intent, MessageType::Reject, None).await?;
     }
-    
     Ok(())
 }
 
 pub async fn send_intent(resource_name: String, intent: &str, id: i64) -> BoxResult<()> {
-    let r_m = INTERNET_RESOURCES.lock().await;
-    let r = r_m.get(&resource_name);
-    if r.is_some() {
-        match send_message_internet(r.unwrap().lock().await, intent, MessageType::Intent, Some(id)).await {
-            Ok(()) => (),
-            Err(e) => return Err(e),
-        }
+    if let Some(r) = get_resource(&resource_name).await {
+        r.lock().await.send(intent, MessageType::Intent, Some(id)).await?;
     }
-    let _ = r;
-
-    let r_m = BLUETOOTH_RESOURCES.lock().await;
-    let r = r_m.get(&resource_name);
-    if r.is_some() {
-        match send_message_bluetooth(Arc::clone(r.unwrap()), intent, MessageType::Intent, Some(id)).await {
-            Ok(_) => (),
-            Err(e) => return Err(e),
-        }
-    }
-    let _ = r; 
-
     if resource_name == "TAPE" {
-        match TAPE.lock().await.copy() {
-            ResourceType::Bluetooth => {
-                match send_message_bluetooth(Arc::clone(BTAPE.lock().await.as_ref().unwrap()), intent, MessageType::Intent, Some(id)).await {
-                    Ok(()) => (),
-                    Err(e) => return Err(e),
-                }
-            },
-            ResourceType::Internet => {
-                // TODO: may error here.
-                match send_message_internet(ITAPE.lock().await, intent, MessageType::Intent, Some(id)).await {
-                    Ok(()) => (),
-                    Err(e) => return Err(e),
-                }
-            },
-            _ => (),
-        }
-
+        send_tape(intent, MessageType::Intent, Some(id)).await?;
     }
-    
     Ok(())
-}
\ No newline at end of file
+}
@@ -4,8 +4,12 @@ use log::warn;
 use regex::Regex;
 use crate::{
     tools::llmq::prompt,
-    base::intent::{Intent, SubIntent},
-    components::linkhub::seeker::get_all_resource_info, 
+    base::{
+        acl::{Principal, ACL},
+        intent::{Intent, SubIntent},
+        resource::{is_in_area, Place},
+    },
+    components::linkhub::seeker::{get_all_resource_info, get_resource_capability, get_resource_status}, 
 };
 
 pub async fn disassembler(intent: &mut Intent) -> Option<()> {
@@ -13,30 +17,41 @@ pub async fn disassembler(intent: &mut Intent) -> Option<()> {
     let sub_intents: Vec<SubIntent>;
     let mut tries_count = 3;
     let mut last_outcome = "".to_string();
+    let principals = intent.principals();
     loop {  
         let rough_intent = 
             disassemble_intent(
                 intent.get_description(), 
-                last_outcome.as_str()
+                last_outcome.as_str(),
+                intent.get_place(),
+                &principals,
             ).await;
 
         last_outcome = rough_intent.clone();
         // warn!("rough disassembled intent: {}", rough_intent);
             
         let to_parse_intent = rough_intent;
-        match format_check(&to_parse_intent) {
-            true => {
-                sub_intents = parse_rough_intent(to_parse_intent);
-                break;
+        if format_check(&to_parse_intent) {
+            let mut parsed = parse_rough_intent(to_parse_intent);
+            for s_intent in parsed.iter_mut() {
+                s_intent.set_place(intent.get_place().cloned());
+                s_intent.set_principals(principals.clone());
             }
-            false => {
-                tries_count -= 1;
-                if tries_count == 0 {
-                    warn!("disassembler: sub_intents error");
-                    return None;
-                }
+            match validate_resources(&mut parsed).await {
+                Ok(()) => {
+                    sub_intents = parsed;
+                    break;
+                },
+                // the LLM may still give resources which are not permitted, tell the reason if we give up.
+                Err(Some(reason)) => intent.set_reject_reason(reason),
+                Err(None) => (),
             }
         }
+        tries_count -= 1;
+        if tries_count == 0 {
+            warn!("disassembler: sub_intents error");
+            return None;
+        }
     }
     if !sub_intents.is_empty() {
         intent.add_sub_intent(sub_intents);
@@ -45,8 +60,8 @@ pub async fn disassembler(intent: &mut Intent) -> Option<()> {
     Some(())
 }
 
-async fn disassemble_intent(intent: &str, last_outcome: &str) -> String {
-    let resource_info = get_all_resource_info().await;
+async fn disassemble_intent(intent: &str, last_outcome: &str, area: Option<&Place>, principals: &[Principal]) -> String {
+    let resource_info = get_all_resource_info(area, principals).await;
     let s_prompt = 
     "
 The user will provide description of Intent, last outcome and information about all available resources, Resources will be given in format: `type_name/description/status`.
@@ -123,6 +138,41 @@ fn format_check(rough_intent: &str) -> bool {
     result
 }
 
+// remove resources which do not exist, out of the area, not permitted or whose capability can not be executed now.
+// error means some sub-intent has no resource left, with the missing permission if it is the reason.
+async fn validate_resources(sub_intents: &mut [SubIntent]) -> Result<(), Option<String>> {
+    for s_intent in sub_intents.iter_mut() {
+        let mut invalid: Vec<String> = vec![];
+        let mut denied: Option<String> = None;
+        for r in s_intent.iter_available_resources() {
+            if let Err(p) = ACL.lock().await.check(s_intent.get_principals(), r, None) {
+                denied = Some(p.to_string());
+                invalid.push(r.clone());
+                continue;
+            }
+            let valid = match get_resource_status(r).await {
+                Some(status) => {
+                    let capability = get_resource_capability(r).await;
+                    is_in_area(status.get_place(), s_intent.get_place())
+                    && (capability.is_empty() || capability.is_ready(&status))
+                },
+                None => false,
+            };
+            if !valid {
+                invalid.push(r.clone());
+            }
+        }
+        for r in invalid {
+            warn!("disassembler: {} is not valid for `{}`", r, s_intent.get_description());
+            s_intent.remove_resource(r);
+        }
+        if s_intent.is_empty() {
+            return Err(denied);
+        }
+    }
+    Ok(())
+}
+
 fn parse_rough_intent(rough_intent: String) -> Vec<SubIntent> {
     let mut sub_intents: Vec<SubIntent> = vec![];
     let sub_intents_pairs = rough_intent.split(";").filter(|s| !s.is_empty()).collect::<Vec<&str>>();
@@ -12,7 +12,8 @@ use crate::{
         router::router,
         disassembler::disassembler, 
         preprocess::{process, JudgeResult}, 
-    }
+    },
+    tools::{event::{emit, emit_with_explanation, EventKind}, record::{record, record_intent}},
 };
 
 
@@ -21,24 +22,40 @@ use crate::{
 // consists of filter, disassembler, router, verifier, monitor.
 pub async fn handler(mut intent: Intent) -> JudgeResult {
     // info!("handler: Start to execute intent");
+    emit(EventKind::Received, intent.get_id(), None, intent.get_resource().map(|r| r.as_str()), intent.get_description());
+    record_intent(&intent);
 
     // preprocess the intent, including filter and special execution.
     match process(&mut intent).await {
         JudgeResult::Execution => {
             return JudgeResult::Execution;
         },
-        JudgeResult::Reject(e) => {
-            return JudgeResult::Reject(e);
+        JudgeResult::Reject(e, x) => {
+            let reason = intent.get_reject_reason().unwrap_or(e.clone());
+            match &x {
+                Some(x) => emit_with_explanation(EventKind::Rejected, intent.get_id(), &reason, x.clone()),
+                None => emit(EventKind::Rejected, intent.get_id(), None, None, &reason),
+            }
+            record(format!("intent {} is rejected: {}", intent.get_id(), reason));
+            return JudgeResult::Reject(e, x);
         },
         JudgeResult::Accept => (),
     }
     
     // disassemble the intent.
+    let id = intent.get_id();
     match disassembler(&mut intent).await {
         Some(_) => {
+            for s_intent in intent.iter_sub_intent() {
+                emit(EventKind::Disassembled, id, Some(s_intent.get_id()), None, s_intent.get_description());
+            }
         },  
         None => {
-            let _ = reject_intent(intent.get_resource().unwrap().to_string(), intent.get_description());
+            let reason = intent.get_reject_reason().unwrap_or("no resource can deal with the intent".to_string());
+            emit(EventKind::Rejected, id, None, None, &reason);
+            if let Some(r) = intent.get_resource() {
+                let _ = reject_intent(r.to_string(), &reason, None).await;
+            }
         }
     }
     // schedule_intent(&intent);
@@ -80,3 +97,10 @@ pub async fn random_execute(intent: &str, status: Arc<Mutex<Status>>) -> BoxResu
     execute_with_status!(sleep(exec_time), status, exec_time);
     Ok(exec_time.as_secs())
 }
+
+// execute in the time given by the simulator, without blocking other simulated resources.
+pub async fn simulate_execute(intent: &str, status: Arc<Mutex<Status>>, exec_time: Duration) -> BoxResult<u64> {
+    info!("simulate {} in {:?}", intent, exec_time);
+    execute_with_status!(tokio::time::sleep(exec_time).await, status, exec_time);
+    Ok(exec_time.as_secs())
+}
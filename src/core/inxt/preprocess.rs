@@ -6,15 +6,20 @@
 //         ->special_execution            => rule_judge
 //         ->reject
 // any time, true means pass the test.
-use chrono::{Local, Datelike};
-use std::process::Command;
+// every rule judged by filter leaves a decision, a rejected intent is answered with the reason and the explanation, see decision.
+use chrono::{DateTime, Datelike, Utc};
+use log::warn;
 
 use crate::{
-    tools::llmq::prompt,
+    components::linkhub::seeker::get_resource_status,
+    tools::{llmq::prompt, wasmrule::IntentView},
     base::{
+        calendar::HOLIDAYS,
+        decision::{Explanation, RuleDecision, Verdict},
         errort::{BoxResult, JudgeError},
         intent::Intent, 
-        rule::{Rule, RuleDetail, RULES, STATIC_RULES}, 
+        rule::{ordered_static_rules, Effect, Rule, RuleDetail, RuleSet, Strategy, RULES}, 
+        ruleexpr::{FieldValues, RuleContext, RuleExpr},
         staticrule,
     }, 
 };
@@ -22,7 +27,8 @@ use crate::{
 const SPECIAL_ID: i64 = 500;
 
 pub enum JudgeResult {
-    Reject(String),
+    // answer to the originator, with the explanation if rules reject the intent.
+    Reject(String, Option<Explanation>),
     Accept,
     Execution,
 }
@@ -40,9 +46,18 @@ pub async fn process(intent: &mut Intent) -> JudgeResult {
 
     // info!("process: Special execution passed");  
 
-    match filter(intent).await {
+    let mut decisions = vec![];
+    match filter(intent, &mut decisions).await {
         Ok(_) => (),
-        Err(e) => return JudgeResult::Reject(format_reject(intent.get_description(), &format!("{}", e))),
+        Err(e) => {
+            // the rule judged last is the one rejecting the intent.
+            let decided_by = decisions.last().filter(|d| d.get_verdict() == Verdict::Deny).cloned();
+            let explanation = Explanation::new(intent.get_description(), &format!("{}", e), decided_by, decisions);
+            intent.set_reject_reason(explanation.summary());
+            let reject = format_reject(intent.get_description(), &explanation.summary());
+            intent.set_explanation(explanation.clone());
+            return JudgeResult::Reject(reject, Some(explanation));
+        },
     }
     
     // info!("process: Filter passed");
@@ -51,14 +66,14 @@ pub async fn process(intent: &mut Intent) -> JudgeResult {
 }
 
 // filter the unacceptible intent.
-async fn filter(intent: &mut Intent) -> BoxResult<()> {
-    match essential_judge(intent).await {
+async fn filter(intent: &mut Intent, decisions: &mut Vec<RuleDecision>) -> BoxResult<()> {
+    match essential_judge(intent, decisions).await {
         Ok(_) => (),
         Err(e) => {
             return Err(e);
         }
     }
-    match user_judge(intent).await {
+    match user_judge(intent, decisions).await {
         Ok(_) => (),
         Err(e) => {
             return Err(e);
@@ -80,12 +95,12 @@ async fn spec_exec(intent: &mut Intent) -> BoxResult<()> {
         // return Ok(false);
     // }
 
-    for rule in STATIC_RULES.values().into_iter() {
+    for rule in ordered_static_rules() {
             if rule.get_id() < SPECIAL_ID {
             // if rule.get_id() < special_id || i_pair[1] != rule.get_name() {
             continue;
         }
-        match rule_judge(intent, rule).await {
+        match rule_judge(intent, rule, Utc::now()).await {
             Err(e) => {
                 // info!("special execution: {}", rule.get_name());
                 return Err(e);   
@@ -98,25 +113,25 @@ async fn spec_exec(intent: &mut Intent) -> BoxResult<()> {
 }
 
 // should be used to judge  every intent.
-async fn essential_judge(intent: &mut Intent) -> BoxResult<()> {
+async fn essential_judge(intent: &mut Intent, decisions: &mut Vec<RuleDecision>) -> BoxResult<()> {
     // info!("essential judge: ");
     
     // in essential part, all rule's will be hard coded.
     // essential rules will never be expired.
     // other than changing the code, user can't not change the rules.
-    for rule in STATIC_RULES.values() {
+    for rule in ordered_static_rules() {
         if rule.get_id() >= SPECIAL_ID {
                 continue;
         }
         // info!("judge, rule id: {}",rule.get_name() );
-        match rule_judge(intent, rule).await {
+        match decide(intent, rule, Utc::now(), decisions).await {
             Ok(()) => {
                 // info!("judge Pass")
             },
-            Err(_) => {
+            Err(e) => {
                 return Err(Box::new(JudgeError::new(&format!(
-                    "We do not accept such intent for {} reason",
-                    rule.get_name()
+                    "We do not accept such intent for {} reason: {}",
+                    rule.get_name(), e
                 ))));
             },
         }
@@ -127,46 +142,95 @@ async fn essential_judge(intent: &mut Intent) -> BoxResult<()> {
 }
 
 // this judge is conducted depends on intent's attributes.
-async fn user_judge(intent: &mut Intent) -> BoxResult<()> {
+async fn user_judge(intent: &mut Intent, decisions: &mut Vec<RuleDecision>) -> BoxResult<()> {
     // TODO: Maybe rule can be specified for intent type.
-    for rule in RULES.lock().await.iter_rules() {
-        match rule_judge(intent, rule).await {
-            Ok(_) => (),
-            Err(_) => {
-                return Err(Box::new(JudgeError::new(
-                    "We do not accept such intent for user rule reason"
-                )));
+    let rules = RULES.lock().await;
+    set_judge(intent, &rules, Utc::now(), decisions).await
+}
+
+// judge the intent by user rules of the set as if it is given at the time, see user_judge.
+pub async fn set_judge(intent: &mut Intent, rules: &RuleSet, now: DateTime<Utc>, decisions: &mut Vec<RuleDecision>) -> BoxResult<()> {
+    let strategy = rules.get_strategy();
+    for rule in rules.ordered_rules() {
+        // rule_judge fails when the rule matches the intent.
+        let reason = match decide(intent, rule, now, decisions).await {
+            Ok(()) => continue,
+            Err(e) => e,
+        };
+        match (rule.get_effect(), strategy) {
+            (Effect::Allow, Strategy::FirstMatch) => {
+                intent.set_allowed_by(Some(rule.get_id()));
+                return Ok(());
             },
+            (Effect::Allow, Strategy::DenyOverrides) => (),
+            (Effect::Deny, _) => return Err(reason),
         }
     }
-
-    // info!("user judge: Judge passed");
     Ok(())
 }
 
+// judge the intent by the rule and record the decision, fails like rule_judge.
+async fn decide(intent: &mut Intent, rule: &Rule, now: DateTime<Utc>, decisions: &mut Vec<RuleDecision>) -> BoxResult<()> {
+    let active = rule.is_active(now, &*HOLIDAYS.lock().await);
+    let result = rule_judge(intent, rule, now).await;
+    decisions.push(RuleDecision::new(rule, active, result.as_ref().err().map(|e| e.to_string())));
+    result
+}
+
 // judge the intent by user defined rule.
 // if {
 //  judge result is true then prevent intent to be executed.
 //} else {
 //  judge result is false then allow intent to be executed.
 //}
-async fn rule_judge(intent: &mut Intent, rule: &Rule) -> BoxResult<()> {
+// now is when the intent is given, rules about time are judged by it.
+pub async fn rule_judge(intent: &mut Intent, rule: &Rule, now: DateTime<Utc>) -> BoxResult<()> {
     // info!("rule: {}", rule.get_description());
+    if !rule.is_active(now, &*HOLIDAYS.lock().await) {
+        return Ok(());
+    }
     match rule.get_rule_detail() {
         RuleDetail::Source(intent_source) 
             => if intent.get_source() == intent_source { 
                 return Err(Box::new(JudgeError::new("We do not accept intent from the source now.")));
             },
         RuleDetail::Time 
-            => if rule.is_expired() {
-                return Err(Box::new(JudgeError::new("We do not accept intent from the source now.")));
+            => if !rule.is_expired() {
+                return Err(Box::new(JudgeError::new("We do not accept intent at this time.")));
             },
         RuleDetail::Weekday(weekday) => {
-            let now = Local::now().date_naive().weekday();
-            if now == *weekday {
+            let today = rule.local_time(now).weekday();
+            if today == *weekday {
                 return Err(Box::new(JudgeError::new("We do not accept intent today.")));
             }
         },
+        RuleDetail::StatusField(c) => {
+            let holds = get_resource_status(&c.resource).await
+                .is_some_and(|s| s.get_field(&c.field).is_some_and(|v| c.holds(v)));
+            if holds {
+                return Err(Box::new(JudgeError::new(&format!(
+                    "We do not accept intent while {} of {} is {:?} {}.", c.field, c.resource, c.op, c.value
+                ))));
+            }
+        },
+        RuleDetail::Expr(e) => {
+            let fields = expr_fields(e).await;
+            let local = rule.local_time(now);
+            let ctx = RuleContext {
+                source: intent.get_source(),
+                resource: intent.get_resource().map(|r| r.as_str()),
+                // rules about target are judged again when the intent is routed.
+                target: None,
+                description: intent.get_description(),
+                priority: intent.get_priority(),
+                now: local,
+                holiday: HOLIDAYS.lock().await.contains(local.date_naive()),
+                fields: &fields,
+            };
+            if e.eval(&ctx) == Some(true) {
+                return Err(Box::new(JudgeError::new(&format!("We do not accept intent when {}.", e))));
+            }
+        },
         RuleDetail::Prompt(rule_description) => {
             let u_prompt = format!(
                 "the rule description is: {}\n the intent description is: {}.", 
@@ -176,14 +240,20 @@ async fn rule_judge(intent: &mut Intent, rule: &Rule) -> BoxResult<()> {
                 "User will give you some Intent, and you need to judge whether it conform to the sentences describe below
                 '{}'
 
-                if it conform, return true, otherwise return false, do not ouput dot or any other things.
+                if it conform, return true, otherwise return false, do not ouput dot or any other things in the first line.
+                then give the reason of your judgement in one sentence in the second line.
                 You need to be tolerant about some general intent.",
                 rule_description
             );
-            match prompt(&s_prompt,&u_prompt).await.as_str() {
-                "true" => return Err(Box::new(JudgeError::new("We do not accept such intent for reason of risk, privilige, rule limit and so on."))),
-                _ => (),
-            };
+            let answer = prompt(&s_prompt,&u_prompt).await;
+            let (judgement, rationale) = answer.split_once('\n').unwrap_or((&answer, ""));
+            if judgement.trim() == "true" {
+                let mut reason = "We do not accept such intent for reason of risk, privilige, rule limit and so on.".to_string();
+                if !rationale.trim().is_empty() {
+                    reason = format!("{} {}", reason, rationale.trim());
+                }
+                return Err(Box::new(JudgeError::new(&reason)));
+            }
         },
         RuleDetail::Function(rule_func) 
             => {
@@ -191,15 +261,17 @@ async fn rule_judge(intent: &mut Intent, rule: &Rule) -> BoxResult<()> {
                     return Err(Box::new(JudgeError::new("We do not accept such intent.")));
                 }
             },
-        RuleDetail::Program(path) 
-            => {
-                let status = Command::new("rustc")
-                    .arg(path)
-                    .status()
-                    .expect("no such function");
-                if status.success() {
-                    return Err(Box::new(JudgeError::new("We do not accept such intent now.")));
-                }
+        RuleDetail::Program(module) 
+            => match module.judge(&IntentView::new(intent)) {
+                Ok(Some(reason)) => return Err(Box::new(JudgeError::new(&reason))),
+                Ok(None) => (),
+                // a broken module never let intents through: it matches as deny rule and not as allow rule.
+                Err(e) => {
+                    warn!("rule module {} fails: {}", module.get_name(), e);
+                    if rule.get_effect() == Effect::Deny {
+                        return Err(Box::new(JudgeError::new("We do not accept such intent now.")));
+                    }
+                },
             },
         RuleDetail::AsyncF(s) => {
             if *s == "rule" {
@@ -221,6 +293,18 @@ async fn rule_judge(intent: &mut Intent, rule: &Rule) -> BoxResult<()> {
     Ok(())
 }
 
+// look up status fields the expression needs, absent ones are unknown.
+pub async fn expr_fields(e: &RuleExpr) -> FieldValues {
+    let mut fields = FieldValues::new();
+    for (resource, field) in e.fields() {
+        let value = get_resource_status(resource).await.and_then(|s| s.get_field(field).cloned());
+        if let Some(v) = value {
+            fields.insert((resource.to_string(), field.to_string()), v);
+        }
+    }
+    fields
+}
+
 pub fn format_reject(intent: &str, reason: &str) -> String {
     // info!("reject: Reject the intent: {}", intent);
     let response = format!(
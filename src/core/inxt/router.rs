@@ -5,18 +5,34 @@
 
 use std::time::Duration;
 
+use chrono::Utc;
 use log::warn;
 
 use crate::{
     base::{
-        errort::{BoxResult, RouteError}, 
-        intent::{Intent, SubIntent}
+        acl::{Principal, ACL},
+        calendar::HOLIDAYS,
+        capability::{ActionCall, Cost},
+        errort::{BoxResult, InterpretError, RouteError}, 
+        health::{Outcome, HEALTH},
+        preference::{Weights, PREFERENCES},
+        telemetry::{TELEMETRY, TREND_WINDOW},
+        resource::is_in_area,
+        intent::{Intent, IntentSource, SubIntent},
+        rule::{Effect, RuleDetail, Strategy, RULES},
+        ruleexpr::RuleContext,
     }, 
+    core::inxt::preprocess::expr_fields,
     components::linkhub::seeker::{
         add_resource_total_busy, calculate_base_dealing, change_resource_dealing, 
-        get_resource_average_busy, get_resource_description, get_resource_status_str, send_intent
+        get_resource_average_busy, get_resource_capability, get_resource_description, 
+        get_resource_status, get_resource_status_str, send_intent
     }, 
-    tools::llmq::prompt
+    tools::{
+        event::{emit, EventKind},
+        interpreter::select_action,
+        llmq::prompt,
+    },
 };
 
 const RETRY_COUNT: i32 = 3;
@@ -25,15 +41,42 @@ const SCORE_METHOD: &str = "usage";
 // the distributer will distribute the sub-intents from disassembler to the corresponding resource or subsystem.
 pub async fn router(i: &mut Intent) {
     // info!("router: Start to router intent");
+    let id = i.get_id();
+    // what user rules need to know about the intent, sub-intents borrow it later.
+    let (source, from, description, priority) = (i.get_source().clone(), i.get_resource().cloned(), i.get_description().to_string(), i.get_priority());
+    let allowed_by = i.get_allowed_by();
+    for s_intent in i.iter_sub_intent() {
+        for (r, rule) in blocked_by_rules(&source, from.as_deref(), &description, priority, allowed_by, s_intent).await {
+            warn!("refuse to route `{}` to {}: rule `{}`", s_intent.get_description(), r, rule);
+            s_intent.remove_resource(r);
+        }
+    }
     if i.get_emergency() {
         for s_intent in i.iter_sub_intent() {
+            // emergency intent is not an excuse to use resources without permission.
+            let denied = ACL.lock().await.check_all(s_intent.get_principals(), s_intent.iter_available_resources());
+            for (r, p) in denied {
+                warn!("refuse to route `{}` to {}: {}", s_intent.get_description(), r, p);
+                s_intent.remove_resource(r);
+            }
+            let resources = s_intent.iter_available_resources().cloned().collect::<Vec<String>>();
             route_all(s_intent).await.unwrap();
+            for r in resources.iter() {
+                emit(EventKind::Routed, id, Some(s_intent.get_id()), Some(r), s_intent.get_description());
+            }
         }
     } else {
         for s_intent in i.iter_sub_intent() {
-            for _ in 0..RETRY_COUNT {
+            for t in 0..RETRY_COUNT {
                 match reroute(s_intent).await {
-                    Ok(_) => break,
+                    Ok(_) => {
+                        emit(EventKind::Routed, id, Some(s_intent.get_id()), s_intent.get_selected_resource().map(|r| r.as_str()), s_intent.get_description());
+                        break;
+                    },
+                    Err(e) if t == RETRY_COUNT - 1 => {
+                        warn!("fail to route `{}`: {}", s_intent.get_description(), e);
+                        emit(EventKind::Rejected, id, Some(s_intent.get_id()), None, &e.to_string());
+                    },
                     Err(_) => continue
                 };
             }
@@ -41,6 +84,43 @@ pub async fn router(i: &mut Intent) {
     }
 }
 
+// resources which user rules about target forbid the sub-intent to be routed to, with the rule.
+// rules are judged in the order of preprocess, rules after the one which allowed the intent never decide.
+async fn blocked_by_rules(source: &IntentSource, from: Option<&str>, description: &str, priority: i32, allowed_by: Option<i64>, s_intent: &SubIntent) -> Vec<(String, String)> {
+    let mut blocked: Vec<(String, String)> = vec![];
+    // resources allowed by the first matched rule.
+    let mut allowed: Vec<&String> = vec![];
+    // same order as preprocess, rules before holidays.
+    let rules = RULES.lock().await;
+    let holidays = HOLIDAYS.lock().await;
+    let strategy = rules.get_strategy();
+    let ordered = rules.ordered_rules();
+    let end = allowed_by.and_then(|id| ordered.iter().position(|r| r.get_id() == id)).unwrap_or(ordered.len());
+    for rule in ordered[..end].iter() {
+        let e = match rule.get_rule_detail() {
+            RuleDetail::Expr(e) if e.has_target() && rule.is_active(Utc::now(), &holidays) => e,
+            _ => continue,
+        };
+        let fields = expr_fields(e).await;
+        let now = rule.local_time(Utc::now());
+        for r in s_intent.iter_available_resources() {
+            if allowed.contains(&r) || blocked.iter().any(|(b, _)| b == r) {
+                continue;
+            }
+            let ctx = RuleContext { source, resource: from, target: Some(r), description, priority, now, holiday: holidays.contains(now.date_naive()), fields: &fields };
+            if e.eval(&ctx) != Some(true) {
+                continue;
+            }
+            match (rule.get_effect(), strategy) {
+                (Effect::Deny, _) => blocked.push((r.clone(), e.to_string())),
+                (Effect::Allow, Strategy::FirstMatch) => allowed.push(r),
+                (Effect::Allow, Strategy::DenyOverrides) => (),
+            }
+        }
+    }
+    blocked
+}
+
 async fn route_intent(resource_name: &str, intent: &str, id: i64) -> BoxResult<()> {  
     send_intent(resource_name.to_string(), intent, id).await
 }
@@ -48,15 +128,76 @@ async fn route_intent(resource_name: &str, intent: &str, id: i64) -> BoxResult<(
 pub async fn reroute(s_intent: &mut SubIntent)  -> BoxResult<()> {
     let name = select_resource(& s_intent).await;
     if name.is_empty() {
-        return Err(Box::new(RouteError::new("no aviable resource now")));
+        // tell the missing permission if resources are refused because of it.
+        let denied = ACL.lock().await.check_all(s_intent.get_principals(), s_intent.iter_available_resources());
+        return match denied.first() {
+            Some((_, p)) => Err(Box::new(RouteError::new(&format!("no aviable resource now, {}", p)))),
+            None => Err(Box::new(RouteError::new("no aviable resource now"))),
+        };
     }
 
     let s = name.to_string().clone();
     s_intent.set_selected_resource(s.clone());
-    s_intent.remove_resource(s);
+    s_intent.remove_resource(s.clone());
     s_intent.set_routed();
-    let resource = s_intent.get_selected_resource().unwrap();
-    route_intent(resource, s_intent.get_description(), s_intent.get_id()).await
+    // resource with capability receive the action call instead of the description.
+    let body = match bind_action(&s, s_intent.get_description()).await {
+        Ok(Some(call)) => {
+            // principal may use the resource but not this action.
+            let permitted = ACL.lock().await.check(s_intent.get_principals(), &s, Some(&call.action));
+            if let Err(p) = permitted {
+                warn!("refuse to route `{}` to {}: {}", s_intent.get_description(), s, p);
+                change_resource_dealing(&s, false).await;
+                return Err(Box::new(RouteError::new(&p.to_string())));
+            }
+            let body = serde_json::to_string(&call)?;
+            s_intent.set_cost(Some(get_resource_capability(&s).await.cost_of(Some(&call.action))));
+            s_intent.set_action(Some(call));
+            body
+        },
+        Ok(None) => {
+            s_intent.set_cost(Some(get_resource_capability(&s).await.cost_of(None)));
+            s_intent.set_action(None);
+            s_intent.get_description().to_string()
+        },
+        Err(e) => {
+            warn!("{} can not deal with `{}`: {}", s, s_intent.get_description(), e);
+            change_resource_dealing(&s, false).await;
+            return Err(Box::new(RouteError::new(&e)));
+        },
+    };
+    match route_intent(&s, &body, s_intent.get_id()).await {
+        Ok(()) => {
+            HEALTH.lock().await.routed(&s, s_intent.get_id());
+            Ok(())
+        },
+        Err(e) => {
+            // resource refusing the intent by its interpreter is not unhealthy, it just do not suit.
+            match e.downcast_ref::<InterpretError>() {
+                Some(InterpretError::Rejected(r)) => warn!("{} reject `{}`: {}", s, s_intent.get_description(), r),
+                _ => HEALTH.lock().await.record(&s, Outcome::Failure, None),
+            }
+            Err(e)
+        },
+    }
+}
+
+// map the sub-intent onto an action of resource and validate it, None if resource declare no capability.
+async fn bind_action(resource: &str, description: &str) -> Result<Option<ActionCall>, String> {
+    let capability = get_resource_capability(resource).await;
+    if capability.is_empty() {
+        return Ok(None);
+    }
+    let status = match get_resource_status(resource).await {
+        Some(s) => s,
+        None => return Err("resource has been removed".to_string()),
+    };
+    let call = match select_action(&capability, description).await {
+        Ok(c) => c,
+        Err(e) => return Err(format!("no action match: {}", e)),
+    };
+    capability.validate(&call, &status)?;
+    Ok(Some(call))
 }
 
 pub async fn route_all(s_intent: &mut SubIntent)  -> BoxResult<()> {
@@ -68,18 +209,46 @@ pub async fn route_all(s_intent: &mut SubIntent)  -> BoxResult<()> {
     Ok(())
 }
 
+// candidate resource and what it takes to use it, the objective is computed among all candidates.
+struct Candidate<'a> {
+    name: &'a str,
+    score: u64,
+    // expected seconds to finish, including intents it is dealing.
+    time: f64,
+    cost: Cost,
+}
+
 async fn select_resource(s_intent: &SubIntent) -> &str {
-    let mut best_resource: &str = "";
-    let mut best_score = 0;
+    let mut candidates: Vec<Candidate> = vec![];
     for resource in s_intent.iter_available_resources() {
+        if ACL.lock().await.check(s_intent.get_principals(), resource, None).is_err() {
+            continue;
+        }
+        if !HEALTH.lock().await.is_available(resource) {
+            continue;
+        }
+        // resource may move out of the area after disassembled.
+        let place = get_resource_status(resource).await.and_then(|s| s.get_place().cloned());
+        if !is_in_area(place.as_ref(), s_intent.get_place()) {
+            continue;
+        }
         let r = format!("{}", resource);
         let score: u64 = score(s_intent.get_description(), &r).await;
         // error!("{resource}, score {}", u64::MAX - score);
-        if score > best_score {
-            best_score = score;
-            best_resource = resource;
+        if score == 0 {
+            continue;
         }
+        let time = calculate_base_dealing(resource).await.saturating_add(get_resource_average_busy(resource).await.as_secs()) as f64;
+        let cost = get_resource_capability(resource).await.expected_cost();
+        candidates.push(Candidate { name: resource, score, time, cost });
     }
+
+    let user = s_intent.get_principals().iter().find_map(|p| match p {
+        Principal::User(u) => Some(u.as_str()),
+        _ => None,
+    });
+    let weights = PREFERENCES.lock().await.get(user).weights();
+    let best_resource = choose(&candidates, &weights);
             // error!("{best_resource} add one");
     // error!("{best_resource}, score {}", u64::MAX - best_score);
     change_resource_dealing(best_resource, true).await;
@@ -87,6 +256,38 @@ async fn select_resource(s_intent: &SubIntent) -> &str {
     best_resource
 }
 
+// the candidate with the least weighted objective, every term is divided by its max among candidates.
+// load is how far the score is from the best one, scores are too close to u64::MAX to be divided in f64.
+// with only load weighted, it is the candidate with the highest score.
+fn choose<'a>(candidates: &[Candidate<'a>], w: &Weights) -> &'a str {
+    fn normalize(v: f64, max: f64) -> f64 {
+        if max > 0.0 { v / max } else { 0.0 }
+    }
+    let max_of = |f: fn(&Candidate) -> f64| candidates.iter().map(f).fold(0.0, f64::max);
+    let max_score = candidates.iter().map(|c| c.score).max().unwrap_or(0);
+    let min_score = candidates.iter().map(|c| c.score).min().unwrap_or(0);
+    let load = |c: &Candidate| normalize((max_score - c.score) as f64, (max_score - min_score) as f64);
+    let max_time = max_of(|c| c.time);
+    let max_energy = max_of(|c| c.cost.energy);
+    let max_money = max_of(|c| c.cost.money);
+    let max_wear = max_of(|c| c.cost.wear);
+
+    let mut best_resource = "";
+    let mut best_objective = f64::MAX;
+    for c in candidates.iter() {
+        let objective = w.load * load(c)
+            + w.time * normalize(c.time, max_time)
+            + w.energy * normalize(c.cost.energy, max_energy)
+            + w.money * normalize(c.cost.money, max_money)
+            + w.wear * normalize(c.cost.wear, max_wear);
+        if objective < best_objective {
+            best_objective = objective;
+            best_resource = c.name;
+        }
+    }
+    best_resource
+}
+
 async fn score(sub_intent: &str, resource: &str) -> u64 {
     match SCORE_METHOD {
         "ai" => {
@@ -95,7 +296,14 @@ async fn score(sub_intent: &str, resource: &str) -> u64 {
         "usage" => {
             let base = 1 + calculate_base_dealing(resource).await;
             let total = 1 + add_resource_total_busy(resource, Duration::from_secs(0)).await.as_secs();
-            u64::MAX - base * total
+            // resource whose load is rising recently is going to be busy.
+            let (rising, availability) = match TELEMETRY.lock().await.aggregate(resource, TREND_WINDOW) {
+                Some(a) => (a.get_dealing_trend().max(0.0).ceil() as u64, a.get_availability()),
+                None => (0, 1.0),
+            };
+            // unhealthy or unstable resource get lower score even it is not busy.
+            let health = (HEALTH.lock().await.score(resource) * availability * 100.0) as u64 + 1;
+            (u64::MAX - base.saturating_mul(total).saturating_mul(1 + rising)) / 101 * health
         },
         _ => {
             // which means just use resource in turn
@@ -12,6 +12,7 @@ pub mod core {
 pub mod components {
     pub mod linkhub {
         pub mod seeker;
+        pub mod registry;
         pub mod waiter;
         pub mod bluetooth {
             pub mod seek;
@@ -26,6 +27,15 @@ pub mod components {
             pub mod seek;
             pub mod wait;
             pub mod resource;
+            pub mod owner;
+        }
+        pub mod mqtt {
+            pub mod seek;
+            pub mod resource;
+        }
+        pub mod coap {
+            pub mod seek;
+            pub mod resource;
         }
     }
 }
@@ -36,15 +46,32 @@ pub mod tools {
     pub mod interpreter;
     pub mod record;
     pub mod rserver;
+    pub mod event;
+    pub mod wserver;
+    pub mod discovery;
+    pub mod simulator;
+    pub mod script;
+    pub mod ruleadmin;
+    pub mod wasmrule;
+    pub mod dryrun;
 }
 
 pub mod base {
     pub mod message;
     pub mod resource;
     pub mod rule;
+    pub mod ruleexpr;
     pub mod intent;
     pub mod staticrule;
     pub mod errort;
+    pub mod capability;
+    pub mod health;
+    pub mod telemetry;
+    pub mod region;
+    pub mod acl;
+    pub mod calendar;
+    pub mod decision;
+    pub mod preference;
 }
 
 pub mod resourcepool;
\ No newline at end of file
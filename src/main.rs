@@ -1,6 +1,6 @@
 use log::info;
 use tapeos::{
-    components::linkhub::internet::{seek::seek, wait::wait}, resourcepool::{DESCRIPTION_VEC, MYSQL_DESCRIPTION, NAME_VEC}, tools::{idgen::init_id_generator, rserver::tape_server}
+    base::rule::keep_rules, components::linkhub::internet::{seek::seek, wait::wait}, resourcepool::MYSQL_DESCRIPTION, tools::{idgen::init_id_generator, rserver::tape_server, wserver::event_server}
 };
 use std::{thread::sleep, time::Duration,};
 
@@ -10,10 +10,16 @@ async fn main() {
     env_logger::init();
     init_id_generator();
 
+    tokio::spawn(keep_rules());
+
     tokio::spawn(async {
         tape_server();
     });
 
+    tokio::spawn(async {
+        let _ = event_server().await;
+    });
+
     tokio::spawn(async {
         let _ = wait("MySQL".to_string(), MYSQL_DESCRIPTION.to_string(), 8001).await;
     });
@@ -29,31 +35,8 @@ async fn main() {
     tokio::spawn(async move {
         let _ = seek().await;
     });
+    // resources of the catalog can be simulated by the `simulator` binary.
     sleep(Duration::from_secs(1000));
     
     info!("main: Try ended");
 }
-
-#[allow(unused)]
-fn loop_resource(times: u16, name: String, desc: String, start_port: u16) {
-    for i in 0..times {
-        let n = name.clone();
-        let d = desc.clone();
-        tokio::spawn(async move {
-            let s = format!("{}{}", n, i);
-            let _ = wait(s, d, start_port+i).await;
-        });
-    }
-}
-
-#[allow(unused)]
-fn start_all_resource() {
-    for i in 0..NAME_VEC.len() {
-        let s = NAME_VEC[i].to_string();
-        let d = DESCRIPTION_VEC[i].to_string();
-        let port_alias = i as u16;
-        tokio::spawn( async move{
-            let _ = wait(s, d, 9000+port_alias).await;
-        });
-    }
-}
\ No newline at end of file
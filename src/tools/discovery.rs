@@ -0,0 +1,176 @@
+// in this file, we will discover TAPEs and the register server on LAN by mDNS/DNS-SD,
+// so that peers can connect to each other without knowing the address in advance.
+// TAPE     _tape._udp.local.           port is the input port of TAPE, TXT records:
+//                                      oport   output port of TAPE,
+//                                      regions json of service regions,
+//                                      load    number of resources it is serving.
+// register _tape-register._udp.local.  port is the port of register server.
+// waiter browse for TAPEs and choose the one whose region suit it best, and the less loaded
+// one if regions are equally suitable. register server is used when no TAPE suit it.
+
+use std::{
+    collections::HashMap,
+    net::{IpAddr, Ipv4Addr, SocketAddr},
+    time::Duration,
+};
+use lazy_static::lazy_static;
+use log::{info, warn};
+use mdns_sd::{ServiceDaemon, ServiceEvent, ServiceInfo};
+use tokio::{sync::Mutex, time::timeout};
+
+use crate::base::{
+    errort::BoxResult,
+    region::{best_region, Region},
+    resource::{Place, Position, RegisterServer},
+};
+
+pub const TAPE_SERVICE: &str = "_tape._udp.local.";
+pub const REGISTER_SERVICE: &str = "_tape-register._udp.local.";
+// how long we wait for answers when browsing.
+const BROWSE_D: Duration = Duration::from_secs(2);
+
+lazy_static! {
+    static ref DAEMON: Option<ServiceDaemon> = match ServiceDaemon::new() {
+        Ok(d) => Some(d),
+        Err(e) => {
+            warn!("mdns is not available: {}", e);
+            None
+        },
+    };
+    // register server in use, local one until we discover another.
+    pub static ref REGISTER: Mutex<SocketAddr> = Mutex::new(SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 8000));
+}
+
+// TAPE found on LAN.
+#[derive(Debug, Clone)]
+pub struct TapeRecord {
+    iaddr: SocketAddr,
+    oaddr: SocketAddr,
+    regions: Vec<Region>,
+    load: u64,
+}
+
+impl TapeRecord {
+    pub fn get_iaddr(&self) -> SocketAddr {
+        self.iaddr
+    }
+
+    pub fn get_load(&self) -> u64 {
+        self.load
+    }
+
+    // in the same form as the answer of register server.
+    pub fn to_register_server(&self) -> RegisterServer {
+        RegisterServer::new(true, Some(self.iaddr), Some(self.oaddr), self.regions.clone(), Position::default(), None)
+    }
+}
+
+fn daemon() -> BoxResult<&'static ServiceDaemon> {
+    DAEMON.as_ref().ok_or("mdns is not available".into())
+}
+
+fn host_name() -> String {
+    format!("tape-{}.local.", std::process::id())
+}
+
+// advertise again with the same port will update the records, such as load.
+pub fn advertise_tape(iport: u16, oport: u16, regions: &[Region], load: u64) -> BoxResult<()> {
+    let mut properties = HashMap::new();
+    properties.insert("oport".to_string(), oport.to_string());
+    properties.insert("load".to_string(), load.to_string());
+    let regions = serde_json::to_string(regions)?;
+    // a TXT record can not be longer than 255 bytes, TAPE with large regions can only be found by register server.
+    if regions.len() + "regions=".len() <= u8::MAX as usize {
+        properties.insert("regions".to_string(), regions);
+    } else {
+        warn!("regions are too large to advertise");
+    }
+    let info = ServiceInfo::new(TAPE_SERVICE, &format!("tape-{iport}"), &host_name(), "", iport, properties)?
+        .enable_addr_auto();
+    daemon()?.register(info)?;
+    Ok(())
+}
+
+pub fn advertise_register(port: u16) -> BoxResult<()> {
+    let info = ServiceInfo::new(REGISTER_SERVICE, &format!("register-{port}"), &host_name(), "", port, None)?
+        .enable_addr_auto();
+    daemon()?.register(info)?;
+    Ok(())
+}
+
+// collect services of the type answered in BROWSE_D.
+async fn browse(service: &str) -> BoxResult<Vec<(SocketAddr, HashMap<String, String>)>> {
+    let receiver = daemon()?.browse(service)?;
+    let mut found = vec![];
+    let _ = timeout(BROWSE_D, async {
+        while let Ok(event) = receiver.recv_async().await {
+            if let ServiceEvent::ServiceResolved(s) = event {
+                let ip = match s.get_addresses_v4().into_iter().next() {
+                    Some(ip) => ip,
+                    None => continue,
+                };
+                let properties = s.txt_properties.iter()
+                    .map(|p| (p.key().to_string(), p.val_str().to_string()))
+                    .collect::<HashMap<String, String>>();
+                found.push((SocketAddr::new(IpAddr::V4(ip), s.port), properties));
+            }
+        }
+    }).await;
+    let _ = daemon()?.stop_browse(service);
+    Ok(found)
+}
+
+pub async fn browse_tapes() -> Vec<TapeRecord> {
+    let found = match browse(TAPE_SERVICE).await {
+        Ok(f) => f,
+        Err(e) => {
+            warn!("browse TAPE error: {}", e);
+            return vec![];
+        },
+    };
+    found.into_iter().filter_map(|(iaddr, p)| {
+        let oport = p.get("oport")?.parse::<u16>().ok()?;
+        Some(TapeRecord {
+            iaddr,
+            oaddr: SocketAddr::new(iaddr.ip(), oport),
+            regions: p.get("regions").and_then(|r| serde_json::from_str(r).ok()).unwrap_or_default(),
+            load: p.get("load").and_then(|l| l.parse().ok()).unwrap_or(0),
+        })
+    }).collect()
+}
+
+// update REGISTER if a register server is found on LAN.
+pub async fn find_register_server() -> SocketAddr {
+    match browse(REGISTER_SERVICE).await {
+        Ok(found) => {
+            if let Some((addr, _)) = found.first() {
+                info!("found register server {}", addr);
+                *REGISTER.lock().await = *addr;
+            }
+        },
+        Err(e) => warn!("browse register server error: {}", e),
+    }
+    *REGISTER.lock().await
+}
+
+// TAPE with the most suitable region for the position, the less loaded one if regions are equally suitable.
+pub fn choose_tape<'a>(tapes: &'a [TapeRecord], position: &Position, place: Option<&Place>) -> Option<&'a TapeRecord> {
+    let mut best: Option<(&TapeRecord, &Region)> = None;
+    for t in tapes.iter() {
+        let region = match best_region(&t.regions, position, place) {
+            Some(r) => r,
+            None => continue,
+        };
+        best = match best {
+            None => Some((t, region)),
+            Some((b, b_region)) => {
+                if region.is_more_suitable(b_region) || (!b_region.is_more_suitable(region) && t.load < b.load) {
+                    Some((t, region))
+                } else {
+                    Some((b, b_region))
+                }
+            },
+        };
+    }
+    best.map(|(t, _)| t)
+}
@@ -0,0 +1,176 @@
+// in this file, we will try rules on recorded intents before they are enabled.
+// a dry run judges every intent of the corpus by user rules twice, by the rules in use and by the
+// candidate, through rule_judge of preprocess, and reports the intents whose outcome changes.
+// candidate is given in json like:
+// {"Rule": TransRule}                                               the rules in use with the new rule.
+// {"RuleSet": {"strategy": "DenyOverrides", "rules": [TransRule]}}  all user rules are replaced.
+// corpus is "AuditLog" for intents in RECORD_FILE, or {"File": "intents.jsonl"} with one
+// RecordedIntent per line, lines of the audit log are accepted too.
+// intents are judged at the time they are recorded, but status of resources and answers of model
+// are the current ones. essential rules are not judged, they never change by rules of users.
+
+use std::{fs, path::PathBuf};
+use log::warn;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{
+    base::{
+        decision::RuleDecision,
+        errort::BoxResult,
+        rule::{RuleSet, Strategy, TransRule, RULES},
+    },
+    core::inxt::preprocess::set_judge,
+    tools::record::{Record, RecordedIntent, RECORD_FILE},
+};
+
+#[derive(Serialize, Deserialize)]
+pub enum Candidate {
+    Rule(Box<TransRule>),
+    RuleSet {
+        #[serde(default)]
+        strategy: Strategy,
+        rules: Vec<TransRule>,
+    },
+}
+
+#[derive(Serialize, Deserialize)]
+pub enum Corpus {
+    AuditLog,
+    File(PathBuf),
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub enum Outcome {
+    Accept,
+    // rule is the deny rule which rejects the intent.
+    Reject { reason: String, rule: Option<RuleDecision> },
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct Change {
+    intent: RecordedIntent,
+    before: Outcome,
+    after: Outcome,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct DryRunReport {
+    total: usize,
+    rejected_before: usize,
+    rejected_after: usize,
+    changed: Vec<Change>,
+}
+
+impl Corpus {
+    fn path(&self) -> PathBuf {
+        match self {
+            Corpus::AuditLog => PathBuf::from(RECORD_FILE),
+            Corpus::File(p) => p.clone(),
+        }
+    }
+
+    // lines which are neither intents nor records of intents are skipped.
+    pub fn load(&self) -> BoxResult<Vec<RecordedIntent>> {
+        let path = self.path();
+        let mut intents = vec![];
+        let data = fs::read_to_string(&path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        for (n, line) in data.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let value: Value = match serde_json::from_str(line) {
+                Ok(v) => v,
+                Err(e) => {
+                    warn!("skip line {} of {}: {}", n + 1, path.display(), e);
+                    continue;
+                },
+            };
+            let intent = if value.get("action").is_some() {
+                serde_json::from_value::<Record>(value).map(|r| r.intent)
+            } else {
+                serde_json::from_value::<RecordedIntent>(value).map(Some)
+            };
+            match intent {
+                Ok(Some(i)) => intents.push(i),
+                Ok(None) => (),
+                Err(e) => warn!("skip line {} of {}: {}", n + 1, path.display(), e),
+            }
+        }
+        Ok(intents)
+    }
+}
+
+impl Outcome {
+    pub fn is_reject(&self) -> bool {
+        matches!(self, Outcome::Reject { .. })
+    }
+}
+
+impl DryRunReport {
+    pub fn get_total(&self) -> usize {
+        self.total
+    }
+
+    pub fn get_changed(&self) -> &[Change] {
+        &self.changed
+    }
+}
+
+impl Change {
+    pub fn get_intent(&self) -> &RecordedIntent {
+        &self.intent
+    }
+
+    pub fn get_before(&self) -> &Outcome {
+        &self.before
+    }
+
+    pub fn get_after(&self) -> &Outcome {
+        &self.after
+    }
+}
+
+// how user rules of the set decide on the intent.
+async fn judge(recorded: &RecordedIntent, rules: &RuleSet) -> Outcome {
+    let mut intent = recorded.to_intent();
+    let mut decisions = vec![];
+    match set_judge(&mut intent, rules, recorded.time, &mut decisions).await {
+        Ok(()) => Outcome::Accept,
+        // the rule judged last is the one rejecting the intent.
+        Err(e) => Outcome::Reject { reason: e.to_string(), rule: decisions.pop() },
+    }
+}
+
+pub async fn dry_run(candidate: Candidate, corpus: &Corpus) -> BoxResult<DryRunReport> {
+    let intents = corpus.load()?;
+    // rules are copied, so that intents are not blocked while the corpus is judged.
+    let current = RULES.lock().await.snapshot();
+    let trial = match candidate {
+        Candidate::Rule(rule) => {
+            let mut set = current.snapshot();
+            set.add_rule(rule.into_rule(None));
+            set
+        },
+        Candidate::RuleSet { strategy, rules } => {
+            let mut set = RuleSet::new(None);
+            set.set_strategy(strategy);
+            for rule in rules {
+                set.add_rule(rule.into_rule(None));
+            }
+            set
+        },
+    };
+
+    let mut report = DryRunReport { total: intents.len(), rejected_before: 0, rejected_after: 0, changed: vec![] };
+    for intent in intents {
+        let before = judge(&intent, &current).await;
+        let after = judge(&intent, &trial).await;
+        report.rejected_before += before.is_reject() as usize;
+        report.rejected_after += after.is_reject() as usize;
+        if before.is_reject() != after.is_reject() {
+            report.changed.push(Change { intent, before, after });
+        }
+    }
+    Ok(report)
+}
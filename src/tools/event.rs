@@ -0,0 +1,123 @@
+// in this file, we will define the lifecycle events of intents.
+// events are broadcast inside the TAPE and streamed to clients by the event server.
+//
+// every event is serialized as one JSON object:
+// {
+//     "kind": "Received" | "Disassembled" | "Routed" | "Rerouted" | "Rejected" | "SubComplete" | "Complete",
+//     "intent_id": i64,
+//     "sub_intent_id": i64 | null,
+//     "resource": string | null,    // resource the (sub-)intent is routed to or comes from.
+//     "description": string,        // description of the (sub-)intent or the reject reason.
+//     "timestamp": i64,             // unix time in milliseconds.
+//     "cost": {"energy": f64, "money": f64, "wear": f64},  // only in SubComplete and Complete.
+//     "explanation": Explanation    // only in Rejected by rules, see decision.
+// }
+
+use chrono::Local;
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast::{self, Receiver, Sender};
+
+use crate::base::{capability::Cost, decision::Explanation};
+
+const EVENT_CAPACITY: usize = 1024;
+
+lazy_static! {
+    pub static ref EVENTS: Sender<IntentEvent> = broadcast::channel(EVENT_CAPACITY).0;
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub enum EventKind {
+    Received,
+    Disassembled,
+    Routed,
+    Rerouted,
+    Rejected,
+    SubComplete,
+    Complete,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct IntentEvent {
+    kind: EventKind,
+    intent_id: i64,
+    sub_intent_id: Option<i64>,
+    resource: Option<String>,
+    description: String,
+    timestamp: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    cost: Option<Cost>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    explanation: Option<Explanation>,
+}
+
+impl IntentEvent {
+    pub fn new(kind: EventKind, intent_id: i64, sub_intent_id: Option<i64>, resource: Option<String>, description: &str) -> Self {
+        Self {
+            kind,
+            intent_id,
+            sub_intent_id,
+            resource,
+            description: description.to_string(),
+            timestamp: Local::now().timestamp_millis(),
+            cost: None,
+            explanation: None,
+        }
+    }
+
+    pub fn with_cost(mut self, cost: Cost) -> Self {
+        self.cost = Some(cost);
+        self
+    }
+
+    pub fn with_explanation(mut self, explanation: Explanation) -> Self {
+        self.explanation = Some(explanation);
+        self
+    }
+
+    pub fn get_kind(&self) -> &EventKind {
+        &self.kind
+    }
+
+    pub fn get_intent_id(&self) -> i64 {
+        self.intent_id
+    }
+
+    pub fn get_sub_intent_id(&self) -> Option<i64> {
+        self.sub_intent_id
+    }
+
+    pub fn get_resource(&self) -> Option<&String> {
+        self.resource.as_ref()
+    }
+
+    pub fn get_cost(&self) -> Option<Cost> {
+        self.cost
+    }
+
+    pub fn get_explanation(&self) -> Option<&Explanation> {
+        self.explanation.as_ref()
+    }
+}
+
+// emit an event to all subscribers, nothing happens if no one is listening.
+pub fn emit(kind: EventKind, intent_id: i64, sub_intent_id: Option<i64>, resource: Option<&str>, description: &str) {
+    let event = IntentEvent::new(kind, intent_id, sub_intent_id, resource.map(|r| r.to_string()), description);
+    let _ = EVENTS.send(event);
+}
+
+// emit an event telling what the (sub-)intent cost.
+pub fn emit_with_cost(kind: EventKind, intent_id: i64, sub_intent_id: Option<i64>, resource: Option<&str>, description: &str, cost: Cost) {
+    let event = IntentEvent::new(kind, intent_id, sub_intent_id, resource.map(|r| r.to_string()), description).with_cost(cost);
+    let _ = EVENTS.send(event);
+}
+
+// emit the event of intent rejected by rules, with how they decide.
+pub fn emit_with_explanation(kind: EventKind, intent_id: i64, description: &str, explanation: Explanation) {
+    let event = IntentEvent::new(kind, intent_id, None, None, description).with_explanation(explanation);
+    let _ = EVENTS.send(event);
+}
+
+pub fn subscribe() -> Receiver<IntentEvent> {
+    EVENTS.subscribe()
+}
@@ -1,18 +1,164 @@
-// use rust_bert::pipelines::zero_shot_classification::ZeroShotClassificationModel;
+// in this file, we will interpret sub-intents for resources.
+// a resource with capability get its action selected by LLM, and a simple device with
+// `Interpreter::Classification` get one of its commands selected offline by `command_selector`.
+// commands of classification are given like `turn_on: light up the room; it is too dark`,
+// name of the command is before ':' and optional example phrases are after it, separated by ';'.
 
-// use crate::base::errort::BoxResult;
+use std::collections::{HashMap, HashSet};
 
-// pub fn command_selector(command: &[&str], intent: &str) -> BoxResult<String> {
-//     let sequence_classification_model = ZeroShotClassificationModel::new(Default::default ())?;
+use crate::{
+    base::{
+        capability::{ActionCall, Capability},
+        errort::{BoxResult, InterpretError},
+    },
+    tools::llmq::prompt,
+};
 
-//     // let candidate_labels = & ["politics", "public health", "economics", "sports"];
+// map the language of sub-intent onto one action declared by the resource.
+// the outcome is not trusted, it should be validated by the capability.
+pub async fn select_action(capability: &Capability, intent: &str) -> BoxResult<ActionCall> {
+    let s_prompt = format!(
+"We'll give you the actions of a resource in format: 'action_1(param: type unit, optional_param?: type unit), action_2(...)'.
+And you need to choose one action and fill its parameters base on the intent given by user.
+Return only json in format: {{\"action\": \"action_name\", \"params\": {{\"param\": value}}}}, do not return anything others.
 
-//     let output = sequence_classification_model.predict_multilabel(
-//         &[intent],
-//         command,   
-//         None,
-//         128,
-//     )?[0][0].text.clone();
-//     Ok(output)
-// }
+Example Actions:
+heat(temperature: number[30..75] °C), stop()
 
+Example Intent:
+I want to take a bath
+
+Example Output:
+{{\"action\": \"heat\", \"params\": {{\"temperature\": 42}}}}
+
+The actions are '{capability}'.");
+    let u_prompt = format!("intent: {intent}");
+    let outcome = prompt(&s_prompt, &u_prompt).await;
+    let call: ActionCall = serde_json::from_str(outcome.trim())?;
+    Ok(call)
+}
+
+// sub-intent which is not similar enough to any command is rejected.
+const MIN_SIMILARITY: f64 = 0.1;
+const STOP_WORDS: [&str; 24] = [
+    "a", "an", "the", "to", "of", "for", "and", "or", "is", "are", "be", "it",
+    "i", "me", "my", "we", "you", "please", "want", "would", "like", "can", "could", "this",
+];
+
+// lowercase words without stop words, with plural and tense roughly removed.
+fn tokenize(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(|w| w.to_lowercase())
+        .filter(|w| !STOP_WORDS.contains(&w.as_str()))
+        .map(|w| {
+            for (suffix, min) in [("ing", 5), ("ed", 4), ("es", 4), ("s", 3)] {
+                if w.len() > min && w.ends_with(suffix) {
+                    return w[..w.len() - suffix.len()].to_string();
+                }
+            }
+            w
+        })
+        .collect()
+}
+
+// name of the command and the words describing it.
+fn parse_command(command: &str) -> (&str, Vec<String>) {
+    let (name, phrases) = command.split_once(':').unwrap_or((command, ""));
+    let name = name.trim();
+    let mut words = tokenize(&name.replace(['_', '-'], " "));
+    words.extend(tokenize(phrases));
+    (name, words)
+}
+
+fn tf_idf(words: &[String], idf: &HashMap<&str, f64>) -> HashMap<String, f64> {
+    let mut v: HashMap<String, f64> = HashMap::new();
+    for w in words {
+        *v.entry(w.clone()).or_default() += 1.0;
+    }
+    for (w, f) in v.iter_mut() {
+        // words no command know do not help to choose.
+        *f *= idf.get(w.as_str()).copied().unwrap_or(0.0);
+    }
+    v
+}
+
+fn cosine(a: &HashMap<String, f64>, b: &HashMap<String, f64>) -> f64 {
+    let dot: f64 = a.iter().filter_map(|(w, x)| b.get(w).map(|y| x * y)).sum();
+    let norm = |v: &HashMap<String, f64>| v.values().map(|x| x * x).sum::<f64>().sqrt();
+    let n = norm(a) * norm(b);
+    if n == 0.0 { 0.0 } else { dot / n }
+}
+
+// choose the command whose words are the most similar to the sub-intent by TF-IDF, without LLM.
+pub fn command_selector(commands: &[String], intent: &str) -> Result<String, InterpretError> {
+    let docs = commands.iter().map(|c| parse_command(c)).collect::<Vec<(&str, Vec<String>)>>();
+    let n = docs.len() as f64;
+    let mut df: HashMap<&str, f64> = HashMap::new();
+    for (_, words) in docs.iter() {
+        for w in words.iter().collect::<HashSet<&String>>() {
+            *df.entry(w.as_str()).or_default() += 1.0;
+        }
+    }
+    let idf = df.into_iter().map(|(w, d)| (w, ((n + 1.0) / (d + 1.0)).ln() + 1.0)).collect::<HashMap<&str, f64>>();
+
+    let query = tf_idf(&tokenize(intent), &idf);
+    let mut best: Option<(&str, f64)> = None;
+    for (name, words) in docs.iter() {
+        let score = cosine(&query, &tf_idf(words, &idf));
+        if best.is_none_or(|(_, s)| score > s) {
+            best = Some((name, score));
+        }
+    }
+    match best {
+        Some((name, score)) if score >= MIN_SIMILARITY => Ok(name.to_string()),
+        _ => Err(InterpretError::Rejected(format!("no command match `{}`", intent))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tokenize_words() {
+        let cases = [
+            ("Please turn ON the lights", vec!["turn", "on", "light"]),
+            ("I want heating, it is cold!", vec!["heat", "cold"]),
+            ("opened doors closed", vec!["open", "door", "clos"]),
+            ("is it a bus", vec!["bus"]),
+            ("", vec![]),
+        ];
+        for (text, want) in cases {
+            assert_eq!(tokenize(text), want, "{}", text);
+        }
+        assert_eq!(parse_command("turn_on: light up"), ("turn_on", vec!["turn".into(), "on".into(), "light".into(), "up".into()]));
+        assert_eq!(parse_command(" stop "), ("stop", vec!["stop".into()]));
+    }
+
+    #[test]
+    fn select_commands() {
+        let commands = [
+            "turn_on: light up the room; it is too dark",
+            "turn_off: switch the lights off; I want to sleep",
+            "dim: lower the brightness; make it softer",
+        ].map(String::from);
+        let cases = [
+            ("it is dark in here", Some("turn_on")),
+            ("please turn on", Some("turn_on")),
+            ("time to sleep", Some("turn_off")),
+            ("switch off everything", Some("turn_off")),
+            ("brightness is too much, make it lower", Some("dim")),
+            ("order a pizza", None),
+            ("please", None),
+        ];
+        for (intent, want) in cases {
+            match (command_selector(&commands, intent), want) {
+                (Ok(name), Some(want)) => assert_eq!(name, want, "{}", intent),
+                (Err(InterpretError::Rejected(_)), None) => {}
+                (got, _) => panic!("{}: {:?}", intent, got),
+            }
+        }
+        assert!(command_selector(&[], "turn on").is_err());
+    }
+}
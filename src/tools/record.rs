@@ -1,8 +1,86 @@
 // record actions of the system.
+// the audit log RECORD_FILE keeps one json object per line:
+// {"time": "2024-05-01T22:13:05Z", "action": "intent 42 is received", "intent": RecordedIntent | null}
+// intents are recorded when they are received, so that rules can be tried on them later, see dryrun.
 
-use log::info;
+use std::{fs::OpenOptions, io::Write};
+use chrono::{DateTime, Utc};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::base::intent::{Intent, IntentSource, IntentType};
+
+pub const RECORD_FILE: &str = "record.log";
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct RecordedIntent {
+    pub description: String,
+    pub source: IntentSource,
+    #[serde(default)]
+    pub resource: Option<String>,
+    #[serde(default)]
+    pub user: Option<String>,
+    #[serde(default)]
+    pub priority: i32,
+    #[serde(default)]
+    pub emergency: bool,
+    // when the intent is given, now if it is not known.
+    #[serde(default = "Utc::now")]
+    pub time: DateTime<Utc>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct Record {
+    pub time: DateTime<Utc>,
+    pub action: String,
+    #[serde(default)]
+    pub intent: Option<RecordedIntent>,
+}
+
+impl RecordedIntent {
+    pub fn new(intent: &Intent) -> Self {
+        Self {
+            description: intent.get_description().to_string(),
+            source: intent.get_source().clone(),
+            resource: intent.get_resource().cloned(),
+            user: intent.get_user().cloned(),
+            priority: intent.get_priority(),
+            emergency: intent.get_emergency(),
+            time: Utc::now(),
+        }
+    }
+
+    // the intent as it was given, with a new id.
+    pub fn to_intent(&self) -> Intent {
+        let mut intent = Intent::new(self.description.clone(), self.source.clone(), IntentType::Intent, self.resource.clone());
+        if let Some(u) = &self.user {
+            intent.set_user(u.clone());
+        }
+        intent.set_priority(self.priority);
+        if self.emergency {
+            intent.set_emergency();
+        }
+        intent
+    }
+}
+
+fn append(record: &Record) {
+    let result = serde_json::to_string(record).map_err(|e| e.to_string()).and_then(|line| {
+        let mut file = OpenOptions::new().create(true).append(true).open(RECORD_FILE).map_err(|e| e.to_string())?;
+        writeln!(file, "{}", line).map_err(|e| e.to_string())
+    });
+    if let Err(e) = result {
+        warn!("record to {} error: {}", RECORD_FILE, e);
+    }
+}
 
 pub fn record(action: String) {
-    // TODO:
     info!("record: {}", action);
-}
\ No newline at end of file
+    append(&Record { time: Utc::now(), action, intent: None });
+}
+
+pub fn record_intent(intent: &Intent) {
+    let action = format!("intent {} is received", intent.get_id());
+    info!("record: {}", action);
+    append(&Record { time: Utc::now(), action, intent: Some(RecordedIntent::new(intent)) });
+}
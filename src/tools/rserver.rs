@@ -4,9 +4,13 @@ use std::net::{IpAddr, Ipv4Addr, SocketAddr, UdpSocket};
 
 use log::{info, warn};
 
-use crate::base::resource::RegisterServer;
+use crate::{
+    base::{region::Region, resource::RegisterServer},
+    tools::discovery::advertise_register,
+};
 
-const IP: [u8; 4] = [127, 0, 0, 1];
+// listen on all interfaces, so that TAPEs and resources on LAN can register.
+const IP: [u8; 4] = [0, 0, 0, 0];
 const PORT: u16 = 8000;
 
 pub fn tape_server() {
@@ -15,6 +19,9 @@ pub fn tape_server() {
     let ipv4 = IpAddr::V4(v4);
     let addr = SocketAddr::new(ipv4,PORT);
     let socket = UdpSocket::bind(addr).unwrap();
+    if let Err(e) = advertise_register(PORT) {
+        warn!("advertise register server error: {e}");
+    }
     // info!("process on");
     
     let mut buf = [0; 1024];
@@ -32,7 +39,7 @@ pub fn tape_server() {
                         continue;
                     }
                 };
-                let s: RegisterServer = match serde_json::from_str(data) {
+                let mut s: RegisterServer = match serde_json::from_str(data) {
                     Ok(s) => s,
                     Err(e) => {
                         warn!("parse error: {e}");
@@ -40,10 +47,23 @@ pub fn tape_server() {
                     },
                 };
                 // info!("process connect");
+                if s.is_tape() {
+                    // TAPE does not know by which address it is reached, it is where the registration comes from.
+                    s.fill_ip(src.ip());
+                    // TAPE register again when its regions change.
+                    tapes.retain(|t| t.get_iaddr() != s.get_iaddr());
+                    tapes.push(s);
+                    continue;
+                }
+                // regions of TAPEs can overlap, choose the most suitable one.
                 let mut best_suit: Option<&RegisterServer> = None;
+                let mut best_region: Option<&Region> = None;
                 for t in tapes.iter() {
-                    if t.is_position_suit(&s) {
-                        best_suit = Some(t);
+                    if let Some(region) = t.suit_region(&s) {
+                        if best_region.is_none_or(|b| region.is_more_suitable(b)) {
+                            best_suit = Some(t);
+                            best_region = Some(region);
+                        }
                     }
                 }
                 if best_suit.is_some() {
@@ -53,10 +73,6 @@ pub fn tape_server() {
                 } else {
                     warn!("no suit tape {}", src);
                 }
-                
-                if s.is_tape() {
-                    tapes.push(s);
-                }
             },
             Err(e) => {
                 warn!("receive error: {e}");
@@ -0,0 +1,159 @@
+// in this file, we will manage user rules by `MessageType::Admin` messages.
+// body of the message is one request in json:
+// "List"                                        all user rules, in the order they are judged.
+// {"Get": id}                                   one rule.
+// {"Delete": id}
+// {"Replace": {"id": id, "rule": TransRule}}    the new rule keep the id, its validity starts again.
+// {"Strategy": "FirstMatch" | "DenyOverrides"}  how user rules are combined, see rule.
+// {"Module": {"name": "night.wasm", "wasm": base64}}  upload a module for rules like {"Program": "night.wasm"},
+//                                               see wasmrule. rules using the old one keep it until replaced.
+// {"DryRun": {"candidate": Candidate, "corpus": Corpus}}  try rules on recorded intents, see dryrun.
+// TAPE reply with `MessageType::Response` whose body is one of:
+// {"Rules": {"strategy": Strategy, "rules": [RuleInfo]}}, {"Rule": RuleInfo}, {"DryRun": DryRunReport},
+// "Done" or {"Error": "reason"}
+// anyone registered can read rules, only admins in acl can change them or try them, which reads files of TAPE.
+// admins give their token of acl in `m_token` of the message, rules they add belong to them.
+
+use std::time::Duration;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use chrono::{DateTime, Utc};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    base::{
+        acl::ACL,
+        calendar::TimeWindow,
+        rule::{Effect, Rule, Strategy, TransRule, TransRuleDetail, RULES},
+    },
+    tools::{
+        dryrun::{dry_run, Candidate, Corpus, DryRunReport},
+        wasmrule::save_module,
+    },
+};
+
+#[derive(Serialize, Deserialize)]
+pub enum AdminRequest {
+    List,
+    Get(i64),
+    Delete(i64),
+    Replace { id: i64, rule: Box<TransRule> },
+    Strategy(Strategy),
+    Module { name: String, wasm: String },
+    DryRun { candidate: Candidate, corpus: Corpus },
+}
+
+#[derive(Serialize, Deserialize)]
+pub enum AdminReply {
+    Rules { strategy: Strategy, rules: Vec<RuleInfo> },
+    Rule(Box<RuleInfo>),
+    DryRun(Box<DryRunReport>),
+    Done,
+    Error(String),
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct RuleInfo {
+    id: i64,
+    name: String,
+    description: String,
+    author: Option<String>,
+    created_time: DateTime<Utc>,
+    // time left before the rule expire, None if it never expire.
+    remaining: Option<Duration>,
+    window: Option<TimeWindow>,
+    effect: Effect,
+    priority: i32,
+    // None for rules given by code.
+    detail: Option<TransRuleDetail>,
+}
+
+impl AdminRequest {
+    fn admin_only(&self) -> bool {
+        !matches!(self, AdminRequest::List | AdminRequest::Get(_))
+    }
+}
+
+impl RuleInfo {
+    fn new(rule: &Rule) -> Self {
+        Self {
+            id: rule.get_id(),
+            name: rule.get_name().to_string(),
+            description: rule.get_description().to_string(),
+            author: rule.get_author().map(|a| a.to_string()),
+            created_time: rule.get_created_time(),
+            remaining: rule.get_expire_time().map(|t| (t - Utc::now()).to_std().unwrap_or_default()),
+            window: rule.get_window().cloned(),
+            effect: rule.get_effect(),
+            priority: rule.get_priority(),
+            detail: rule.get_rule_detail().to_trans(),
+        }
+    }
+}
+
+// requester is the registered resource which sends the request, token is the one of admin if it is given.
+pub async fn handle_admin(requester: &str, token: Option<&str>, body: &str) -> AdminReply {
+    let request: AdminRequest = match serde_json::from_str(body) {
+        Ok(r) => r,
+        Err(e) => return AdminReply::Error(format!("bad request: {}", e)),
+    };
+    let admin = ACL.lock().await.admin(token).map(|a| a.get_name().to_string());
+    let admin = match admin {
+        Some(a) => a,
+        None if request.admin_only() => {
+            warn!("admin request from {} is refused", requester);
+            return AdminReply::Error("admin only".to_string());
+        },
+        None => requester.to_string(),
+    };
+
+    match request {
+        AdminRequest::List => {
+            let rules = RULES.lock().await;
+            AdminReply::Rules {
+                strategy: rules.get_strategy(),
+                rules: rules.ordered_rules().into_iter().map(RuleInfo::new).collect(),
+            }
+        },
+        AdminRequest::Get(id) => match RULES.lock().await.get_rule_by_id(id) {
+            Some(r) => AdminReply::Rule(Box::new(RuleInfo::new(r))),
+            None => AdminReply::Error(format!("no rule {}", id)),
+        },
+        AdminRequest::Delete(id) => {
+            if !RULES.lock().await.delete_rule(id) {
+                return AdminReply::Error(format!("no rule {}", id));
+            }
+            info!("rule {} is deleted by {}", id, admin);
+            AdminReply::Done
+        },
+        AdminRequest::Replace { id, rule } => {
+            // the rule belongs to the admin, like the rule added by intent.
+            let r = rule.into_rule(Some(admin.clone()));
+            if !RULES.lock().await.replace_rule(id, r) {
+                return AdminReply::Error(format!("no rule {}", id));
+            }
+            info!("rule {} is replaced by {}", id, admin);
+            AdminReply::Done
+        },
+        AdminRequest::Strategy(s) => {
+            RULES.lock().await.set_strategy(s);
+            info!("rule strategy is set to {:?} by {}", s, admin);
+            AdminReply::Done
+        },
+        AdminRequest::Module { name, wasm } => {
+            let result = STANDARD.decode(wasm)
+                .map_err(|e| format!("bad wasm: {}", e))
+                .and_then(|w| save_module(&name, &w));
+            if let Err(e) = result {
+                return AdminReply::Error(e);
+            }
+            info!("rule module {} is uploaded by {}", name, admin);
+            AdminReply::Done
+        },
+        // dry run takes rules by itself, and it may take long.
+        AdminRequest::DryRun { candidate, corpus } => match dry_run(candidate, &corpus).await {
+            Ok(report) => AdminReply::DryRun(Box::new(report)),
+            Err(e) => AdminReply::Error(format!("dry run: {}", e)),
+        },
+    }
+}
@@ -0,0 +1,87 @@
+// in this file, we will run the script interpreter of `Interpreter::PathBuf`.
+// the script read json from stdin: {"intent": "turn on the light", "capability": {...}}
+// where intent is the sub-intent, or the action call if the resource declare capability,
+// and print the device command to stdout. exit status of the script:
+// 0            stdout is the command.
+// REJECT_EXIT  the resource can not deal with the intent, it is rerouted without blaming the resource.
+// others       the script fail, the intent is rerouted and the resource is blamed.
+// scripts are only run from SCRIPT_DIR on TAPE, so that a resource can not make TAPE run
+// anything else by its registration. every run is limited by SCRIPT_TIMEOUT, cpu time and memory.
+
+use std::{
+    path::{Component, Path, PathBuf},
+    process::Stdio,
+    time::Duration,
+};
+use serde::Serialize;
+use tokio::{io::AsyncWriteExt, process::Command, time::timeout};
+
+use crate::base::{capability::Capability, errort::InterpretError};
+
+pub const SCRIPT_DIR: &str = "scripts";
+pub const REJECT_EXIT: i32 = 3;
+const SCRIPT_TIMEOUT: Duration = Duration::from_secs(5);
+// cpu time in seconds and virtual memory in KiB, given to `ulimit`.
+const CPU_LIMIT: u64 = 2;
+const MEMORY_LIMIT: u64 = 256 * 1024;
+// device command is short, longer output means something goes wrong.
+const OUTPUT_LIMIT: usize = 4096;
+
+#[derive(Serialize)]
+struct ScriptInput<'a> {
+    intent: &'a str,
+    capability: &'a Capability,
+}
+
+// path of script must be relative and stay in SCRIPT_DIR.
+fn resolve(path: &Path) -> Result<PathBuf, InterpretError> {
+    if path.as_os_str().is_empty() || !path.components().all(|c| matches!(c, Component::Normal(_))) {
+        return Err(InterpretError::Failed(format!("{} is not a script in {}", path.display(), SCRIPT_DIR)));
+    }
+    Ok(Path::new(SCRIPT_DIR).join(path))
+}
+
+pub async fn run_script(path: &Path, capability: &Capability, intent: &str) -> Result<String, InterpretError> {
+    let script = resolve(path)?;
+    let input = serde_json::to_vec(&ScriptInput { intent, capability }).map_err(|e| InterpretError::Failed(e.to_string()))?;
+
+    // limits are set by the shell before it become the script, script is given as $0 to avoid quoting.
+    let mut child = Command::new("sh")
+        .arg("-c")
+        .arg(format!("ulimit -t {CPU_LIMIT} -v {MEMORY_LIMIT} 2>/dev/null; exec \"$0\""))
+        .arg(&script)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|e| InterpretError::Failed(format!("{}: {}", script.display(), e)))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        // script may exit without reading its input.
+        let _ = stdin.write_all(&input).await;
+    }
+
+    // child is killed when the waiting is dropped by timeout.
+    let output = match timeout(SCRIPT_TIMEOUT, child.wait_with_output()).await {
+        Ok(Ok(o)) => o,
+        Ok(Err(e)) => return Err(InterpretError::Failed(e.to_string())),
+        Err(_) => return Err(InterpretError::Failed(format!("timeout after {:?}", SCRIPT_TIMEOUT))),
+    };
+    let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+    match output.status.code() {
+        Some(0) => {
+            if output.stdout.len() > OUTPUT_LIMIT {
+                return Err(InterpretError::Failed(format!("command is longer than {} bytes", OUTPUT_LIMIT)));
+            }
+            let command = String::from_utf8_lossy(&output.stdout).trim().to_string();
+            if command.is_empty() {
+                return Err(InterpretError::Rejected("no command is given".to_string()));
+            }
+            Ok(command)
+        },
+        Some(REJECT_EXIT) => Err(InterpretError::Rejected(stderr)),
+        Some(c) => Err(InterpretError::Failed(format!("exit with {}: {}", c, stderr))),
+        // killed by signal, such as exceeding the cpu limit.
+        None => Err(InterpretError::Failed(format!("killed: {}", stderr))),
+    }
+}
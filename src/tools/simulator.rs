@@ -0,0 +1,349 @@
+// in this file, we will simulate resources of the catalog in `resourcepool`, so that TAPE
+// can be load tested and demonstrated without real devices.
+// a scenario is configured by json file SCENARIO_FILE, for example:
+// {
+//     "tape": true,
+//     "start_port": 9000,
+//     "duration": 600,
+//     "resources": [
+//         {
+//             "name": "MySQL", "replicas": 3,
+//             "latency": {"Normal": {"mean": 2.0, "std": 0.5}},
+//             "failure_rate": 0.1, "reject_rate": 0.05,
+//             "disconnects": [{"after": 60, "duration": 10, "every": 120}],
+//             "drift": {"velocity": [0.5, 0.0, 0.0], "jitter": 0.1}
+//         },
+//         {"name": "Smart speaker", "latency": {"Uniform": {"min": 1.0, "max": 3.0}}}
+//     ]
+// }
+// tape        also start TAPE and register server in the simulator.
+// start_port  resources listen on ports from it one by one, input ports are 20000 above them.
+// duration    seconds to run, forever if not given.
+// replicas of a resource are named with its index, like `MySQL0`, `MySQL1`.
+// latency is in seconds, a rejected intent is refused at once while a failed one is
+// refused after the latency, both are answered with `Reject` so that TAPE reroute them.
+
+use std::{
+    fs,
+    path::Path,
+    time::{Duration, Instant},
+};
+use log::info;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use tokio::task::JoinHandle;
+
+use crate::{
+    base::{errort::BoxResult, resource::Status},
+    components::linkhub::internet::wait::simulate,
+    resourcepool::{DESCRIPTION_VEC, NAME_VEC},
+};
+
+pub const SCENARIO_FILE: &str = "scenario.json";
+// how often status drift and disconnections are applied.
+pub const DRIFT_D: Duration = Duration::from_secs(1);
+const INPUT_PORT_OFFSET: u16 = 20000;
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Scenario {
+    #[serde(default)]
+    tape: bool,
+    #[serde(default = "default_start_port")]
+    start_port: u16,
+    #[serde(default)]
+    duration: Option<u64>,
+    resources: Vec<ResourceScenario>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ResourceScenario {
+    name: String,
+    // description of the catalog is used if not given.
+    #[serde(default)]
+    description: Option<String>,
+    #[serde(default = "default_replicas")]
+    replicas: u16,
+    #[serde(default)]
+    latency: Latency,
+    #[serde(default)]
+    failure_rate: f64,
+    #[serde(default)]
+    reject_rate: f64,
+    #[serde(default)]
+    disconnects: Vec<Disconnect>,
+    #[serde(default)]
+    drift: Drift,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub enum Latency {
+    Fixed(f64),
+    Uniform { min: f64, max: f64 },
+    Normal { mean: f64, std: f64 },
+}
+
+// offline for duration seconds from after seconds since start, and again every seconds if given.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Disconnect {
+    after: u64,
+    duration: u64,
+    #[serde(default)]
+    every: Option<u64>,
+}
+
+// position move velocity per second, with random jitter on every axis.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct Drift {
+    #[serde(default)]
+    velocity: [f32; 3],
+    #[serde(default)]
+    jitter: f32,
+}
+
+// how a simulated resource deal with an intent.
+#[derive(Debug, Clone, Copy)]
+pub enum Decision {
+    Execute(Duration),
+    Fail(Duration),
+    Reject,
+}
+
+// behavior of one simulated resource.
+#[derive(Debug, Clone)]
+pub struct Behavior {
+    latency: Latency,
+    failure_rate: f64,
+    reject_rate: f64,
+    disconnects: Vec<Disconnect>,
+    drift: Drift,
+    start: Instant,
+}
+
+fn default_start_port() -> u16 {
+    9000
+}
+
+fn default_replicas() -> u16 {
+    1
+}
+
+impl Default for Latency {
+    fn default() -> Self {
+        Latency::Fixed(1.0)
+    }
+}
+
+impl Latency {
+    fn sample(&self) -> Duration {
+        let mut rng = rand::thread_rng();
+        let secs = match *self {
+            Latency::Fixed(s) => s,
+            Latency::Uniform { min, max } if min < max => rng.gen_range(min..max),
+            Latency::Uniform { min, .. } => min,
+            Latency::Normal { mean, std } => {
+                // Box-Muller transform.
+                let u1: f64 = rng.gen_range(f64::EPSILON..1.0);
+                let u2: f64 = rng.gen();
+                mean + std * (-2.0 * u1.ln()).sqrt() * (2.0 * std::f64::consts::PI * u2).cos()
+            },
+        };
+        Duration::from_secs_f64(secs.max(0.0))
+    }
+}
+
+impl Disconnect {
+    fn is_offline(&self, elapsed: u64) -> bool {
+        if elapsed < self.after {
+            return false;
+        }
+        let since = elapsed - self.after;
+        match self.every {
+            Some(every) if every > 0 => since % every < self.duration,
+            _ => since < self.duration,
+        }
+    }
+}
+
+impl Behavior {
+    pub fn decide(&self) -> Decision {
+        let mut rng = rand::thread_rng();
+        if rng.gen_bool(self.reject_rate.clamp(0.0, 1.0)) {
+            return Decision::Reject;
+        }
+        let latency = self.latency.sample();
+        if rng.gen_bool(self.failure_rate.clamp(0.0, 1.0)) {
+            Decision::Fail(latency)
+        } else {
+            Decision::Execute(latency)
+        }
+    }
+
+    pub fn is_offline(&self) -> bool {
+        let elapsed = self.start.elapsed().as_secs();
+        self.disconnects.iter().any(|d| d.is_offline(elapsed))
+    }
+
+    // move the position as it drift in DRIFT_D.
+    pub fn drift(&self, status: &mut Status) {
+        let mut rng = rand::thread_rng();
+        let secs = DRIFT_D.as_secs_f32();
+        let mut p = status.get_position().clone();
+        let mut jitter = || if self.drift.jitter > 0.0 { rng.gen_range(-self.drift.jitter..self.drift.jitter) } else { 0.0 };
+        p.x += self.drift.velocity[0] * secs + jitter();
+        p.y += self.drift.velocity[1] * secs + jitter();
+        p.z += self.drift.velocity[2] * secs + jitter();
+        status.set_position(p);
+    }
+}
+
+impl ResourceScenario {
+    fn get_description(&self) -> BoxResult<String> {
+        if let Some(d) = &self.description {
+            return Ok(d.clone());
+        }
+        match NAME_VEC.iter().position(|n| *n == self.name) {
+            Some(i) => Ok(DESCRIPTION_VEC[i].to_string()),
+            None => Err(format!("{} is not in the catalog, give it a description", self.name).into()),
+        }
+    }
+
+    fn behavior(&self) -> Behavior {
+        Behavior {
+            latency: self.latency.clone(),
+            failure_rate: self.failure_rate,
+            reject_rate: self.reject_rate,
+            disconnects: self.disconnects.clone(),
+            drift: self.drift.clone(),
+            start: Instant::now(),
+        }
+    }
+
+    fn replica_name(&self, i: u16) -> String {
+        if self.replicas == 1 {
+            self.name.clone()
+        } else {
+            format!("{}{}", self.name, i)
+        }
+    }
+}
+
+impl Scenario {
+    pub fn load<P: AsRef<Path>>(path: P) -> BoxResult<Self> {
+        let data = fs::read_to_string(path)?;
+        Ok(serde_json::from_str(&data)?)
+    }
+
+    pub fn with_tape(&self) -> bool {
+        self.tape
+    }
+
+    pub fn get_duration(&self) -> Option<Duration> {
+        self.duration.map(Duration::from_secs)
+    }
+
+    // spawn every replica of the scenario, the scenario is checked before anything is started.
+    pub fn start(&self) -> BoxResult<Vec<JoinHandle<()>>> {
+        let total: u32 = self.resources.iter().map(|r| r.replicas as u32).sum();
+        if self.start_port as u32 + total + INPUT_PORT_OFFSET as u32 > u16::MAX as u32 {
+            return Err("too many resources for start_port".into());
+        }
+        let mut resources = Vec::new();
+        for r in self.resources.iter() {
+            resources.push((r, r.get_description()?));
+        }
+
+        let mut handles = Vec::new();
+        let mut port = self.start_port;
+        for (r, desc) in resources {
+            for i in 0..r.replicas {
+                let name = r.replica_name(i);
+                let desc = desc.clone();
+                let behavior = r.behavior();
+                info!("simulate {} on {}", name, port);
+                handles.push(tokio::spawn(async move {
+                    let _ = simulate(name, desc, port, behavior).await;
+                }));
+                port += 1;
+            }
+        }
+        Ok(handles)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn disconnect_windows() {
+        let once = Disconnect { after: 60, duration: 10, every: None };
+        let periodic = Disconnect { after: 60, duration: 10, every: Some(120) };
+        let never_again = Disconnect { after: 5, duration: 3, every: Some(0) };
+        let cases = [
+            (&once, 59, false),
+            (&once, 60, true),
+            (&once, 69, true),
+            (&once, 70, false),
+            (&once, 180, false),
+            (&periodic, 0, false),
+            (&periodic, 65, true),
+            (&periodic, 70, false),
+            (&periodic, 180, true),
+            (&periodic, 189, true),
+            (&periodic, 190, false),
+            (&never_again, 7, true),
+            (&never_again, 8, false),
+        ];
+        for (d, elapsed, want) in cases {
+            assert_eq!(d.is_offline(elapsed), want, "{:?} at {}", d, elapsed);
+        }
+    }
+
+    #[test]
+    fn latency_samples() {
+        assert_eq!(Latency::Fixed(1.5).sample(), Duration::from_millis(1500));
+        assert_eq!(Latency::Fixed(-1.0).sample(), Duration::ZERO);
+        // range which is empty is its min.
+        assert_eq!(Latency::Uniform { min: 2.0, max: 2.0 }.sample(), Duration::from_secs(2));
+        assert_eq!(Latency::Normal { mean: 3.0, std: 0.0 }.sample(), Duration::from_secs(3));
+
+        let uniform = Latency::Uniform { min: 1.0, max: 3.0 };
+        let normal = Latency::Normal { mean: 2.0, std: 0.5 };
+        let n = 2000;
+        let (mut u_sum, mut n_sum) = (0.0, 0.0);
+        for _ in 0..n {
+            let u = uniform.sample().as_secs_f64();
+            assert!((1.0..3.0).contains(&u), "{}", u);
+            u_sum += u;
+            n_sum += normal.sample().as_secs_f64();
+        }
+        assert!((u_sum / n as f64 - 2.0).abs() < 0.1);
+        assert!((n_sum / n as f64 - 2.0).abs() < 0.1);
+        // negative samples are cut to zero.
+        let negative = Latency::Normal { mean: -5.0, std: 0.1 };
+        assert!((0..100).all(|_| negative.sample() == Duration::ZERO));
+    }
+
+    #[test]
+    fn scenario_of_header() {
+        let scenario: Scenario = serde_json::from_str(r#"{
+            "tape": true,
+            "start_port": 9000,
+            "duration": 600,
+            "resources": [
+                {
+                    "name": "MySQL", "replicas": 3,
+                    "latency": {"Normal": {"mean": 2.0, "std": 0.5}},
+                    "failure_rate": 0.1, "reject_rate": 0.05,
+                    "disconnects": [{"after": 60, "duration": 10, "every": 120}],
+                    "drift": {"velocity": [0.5, 0.0, 0.0], "jitter": 0.1}
+                },
+                {"name": "Smart speaker", "latency": {"Uniform": {"min": 1.0, "max": 3.0}}}
+            ]
+        }"#).unwrap();
+        assert_eq!(scenario.resources.len(), 2);
+        assert_eq!(scenario.resources[0].replicas, 3);
+        assert_eq!(scenario.resources[1].replicas, 1);
+        assert!(scenario.resources[1].disconnects.is_empty());
+    }
+}
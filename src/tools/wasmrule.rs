@@ -0,0 +1,165 @@
+// in this file, we will run rule modules of `RuleDetail::Program` in WebAssembly.
+// a module is kept in MODULE_DIR on TAPE and exports:
+//     memory                             its linear memory.
+//     alloc(len: i32) -> i32             space for the input of given length.
+//     judge(ptr: i32, len: i32) -> i64
+// judge reads the intent view in json from ptr, like
+//     {"description": "...", "source": "Input", "resource": "phone", "user": null, "priority": 0, "emergency": false}
+// and returns 0 to accept the intent, or (ptr << 32 | len) of the utf-8 reason to deny it.
+// a module can import nothing, so it has no way to reach the outside, and every judge is
+// limited by FUEL and MEMORY_LIMIT. modules are uploaded by admins, see ruleadmin, and a rule
+// keeps the module it is compiled with even if the file is uploaded again.
+// a stored rule whose module is gone or no longer compiles is loaded unloaded, every judge of it
+// fails, so that it still denies as deny rule and never allows, and it is saved as it was.
+
+use std::{
+    fs,
+    path::{Component, Path, PathBuf},
+    sync::Arc,
+};
+use lazy_static::lazy_static;
+use log::warn;
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use wasmi::{Config, Engine, Linker, Module, Store, StoreLimits, StoreLimitsBuilder};
+
+use crate::base::intent::{Intent, IntentSource};
+
+pub const MODULE_DIR: &str = "modules";
+// instructions a judge may run, roughly.
+const FUEL: u64 = 1_000_000;
+const MEMORY_LIMIT: usize = 16 * 1024 * 1024;
+// reason longer than this is cut.
+const REASON_LIMIT: usize = 1024;
+
+lazy_static! {
+    static ref ENGINE: Engine = {
+        let mut config = Config::default();
+        config.consume_fuel(true);
+        Engine::new(&config)
+    };
+}
+
+// what a module knows about the intent.
+#[derive(Serialize)]
+pub struct IntentView<'a> {
+    description: &'a str,
+    source: &'a IntentSource,
+    resource: Option<&'a str>,
+    user: Option<&'a str>,
+    priority: i32,
+    emergency: bool,
+}
+
+// a compiled module, it is stored by its name in MODULE_DIR.
+// module is the reason it can not be loaded if it is unloaded.
+#[derive(Clone)]
+pub struct WasmRule {
+    name: String,
+    module: Result<Arc<Module>, String>,
+}
+
+impl<'a> IntentView<'a> {
+    pub fn new(intent: &'a Intent) -> Self {
+        Self {
+            description: intent.get_description(),
+            source: intent.get_source(),
+            resource: intent.get_resource().map(|r| r.as_str()),
+            user: intent.get_user().map(|u| u.as_str()),
+            priority: intent.get_priority(),
+            emergency: intent.get_emergency(),
+        }
+    }
+}
+
+// name of module must be relative and stay in MODULE_DIR.
+fn resolve(name: &str) -> Result<PathBuf, String> {
+    let path = Path::new(name);
+    if name.is_empty() || !path.components().all(|c| matches!(c, Component::Normal(_))) {
+        return Err(format!("{} is not a module in {}", name, MODULE_DIR));
+    }
+    Ok(Path::new(MODULE_DIR).join(path))
+}
+
+fn compile(wasm: &[u8]) -> Result<Module, String> {
+    Module::new(&ENGINE, wasm).map_err(|e| format!("invalid module: {}", e))
+}
+
+// keep the module in MODULE_DIR, it is checked before it is written.
+pub fn save_module(name: &str, wasm: &[u8]) -> Result<(), String> {
+    let path = resolve(name)?;
+    compile(wasm)?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    }
+    fs::write(&path, wasm).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+impl WasmRule {
+    pub fn load(name: &str) -> Result<Self, String> {
+        let path = resolve(name)?;
+        let wasm = fs::read(&path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        Ok(Self { name: name.to_string(), module: Ok(Arc::new(compile(&wasm)?)) })
+    }
+
+    // for rules which are stored already, the rule is kept even if its module can not be loaded.
+    pub fn load_or_unloaded(name: &str) -> Self {
+        Self::load(name).unwrap_or_else(|e| {
+            warn!("rule module {} is unloaded: {}", name, e);
+            Self { name: name.to_string(), module: Err(e) }
+        })
+    }
+
+    pub fn is_loaded(&self) -> bool {
+        self.module.is_ok()
+    }
+
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    // Some(reason) if the module deny the intent.
+    pub fn judge(&self, view: &IntentView) -> Result<Option<String>, String> {
+        let err = |e: wasmi::Error| e.to_string();
+        let module = self.module.as_ref().map_err(|e| format!("module is unloaded: {}", e))?;
+        let input = serde_json::to_vec(view).map_err(|e| e.to_string())?;
+        let len = i32::try_from(input.len()).map_err(|_| "intent is too long".to_string())?;
+
+        let limits = StoreLimitsBuilder::new().memory_size(MEMORY_LIMIT).instances(1).memories(1).tables(1).build();
+        let mut store = Store::new(&ENGINE, limits);
+        store.limiter(|l: &mut StoreLimits| l);
+        store.set_fuel(FUEL).map_err(|e| e.to_string())?;
+        // nothing is linked, module with imports fails here.
+        let instance = Linker::<StoreLimits>::new(&ENGINE)
+            .instantiate(&mut store, module)
+            .and_then(|pre| pre.start(&mut store))
+            .map_err(err)?;
+        let memory = instance.get_memory(&store, "memory").ok_or("no memory is exported".to_string())?;
+        let alloc = instance.get_typed_func::<i32, i32>(&store, "alloc").map_err(err)?;
+        let judge = instance.get_typed_func::<(i32, i32), i64>(&store, "judge").map_err(err)?;
+
+        let ptr = alloc.call(&mut store, len).map_err(err)?;
+        memory.write(&mut store, ptr as u32 as usize, &input).map_err(|e| e.to_string())?;
+        let result = judge.call(&mut store, (ptr, len)).map_err(err)? as u64;
+        if result == 0 {
+            return Ok(None);
+        }
+        let (reason_ptr, reason_len) = ((result >> 32) as usize, ((result & 0xffff_ffff) as usize).min(REASON_LIMIT));
+        let mut reason = vec![0; reason_len];
+        memory.read(&store, reason_ptr, &mut reason).map_err(|e| e.to_string())?;
+        Ok(Some(String::from_utf8_lossy(&reason).to_string()))
+    }
+}
+
+// module is stored as its name and compiled when it is loaded.
+impl Serialize for WasmRule {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.name)
+    }
+}
+
+impl<'de> Deserialize<'de> for WasmRule {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        WasmRule::load(&name).map_err(de::Error::custom)
+    }
+}
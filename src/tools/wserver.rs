@@ -0,0 +1,182 @@
+// wserver is used to stream intent lifecycle events to client apps by websocket.
+// clients choose what to listen by the request path:
+// `/intents`       all events of the TAPE.
+// `/intents/<id>`  events of the intent with given id.
+// `/telemetry/<name>` status history of the resource, client send the window in seconds
+//                  and get {"resource", "unavailable_since", "aggregate", "samples"} as reply.
+
+use std::time::Duration;
+use futures::{SinkExt, StreamExt};
+use log::{info, warn};
+use serde::Serialize;
+use tokio::{
+    net::{TcpListener, TcpStream},
+    sync::broadcast::error::RecvError,
+};
+use tokio_tungstenite::{
+    accept_hdr_async,
+    WebSocketStream,
+    tungstenite::{
+        handshake::server::{ErrorResponse, Request, Response},
+        http::StatusCode,
+        Message,
+    },
+};
+
+use crate::{
+    base::{
+        errort::BoxResult,
+        telemetry::{Aggregate, Sample, TELEMETRY},
+    },
+    tools::event::{subscribe, IntentEvent},
+};
+
+pub const EVENT_ADDRESS: &str = "127.0.0.1:8890";
+// window of telemetry query if client do not give one.
+const TELEMETRY_WINDOW: u64 = 300;
+
+enum Subscription {
+    // None means the client listen to all intents.
+    Intents(Option<i64>),
+    Telemetry(String),
+}
+
+#[derive(Serialize)]
+struct TelemetryReply<'a> {
+    resource: &'a str,
+    unavailable_since: Option<i64>,
+    aggregate: Option<Aggregate>,
+    samples: Vec<Sample>,
+}
+
+pub async fn event_server() -> BoxResult<()> {
+    let listener = TcpListener::bind(EVENT_ADDRESS).await?;
+    info!("event server listen on {}", EVENT_ADDRESS);
+    loop {
+        let (stream, src) = listener.accept().await?;
+        tokio::spawn(async move {
+            if let Err(e) = serve(stream).await {
+                warn!("event stream of {} closed: {}", src, e);
+            }
+        });
+    }
+}
+
+fn parse_path(path: &str) -> Result<Subscription, ()> {
+    let path = path.trim_end_matches('/');
+    if path.is_empty() || path == "/intents" {
+        return Ok(Subscription::Intents(None));
+    }
+    if let Some(name) = path.strip_prefix("/telemetry/") {
+        return decode(name).map(Subscription::Telemetry).ok_or(());
+    }
+    match path.strip_prefix("/intents/") {
+        Some(id) => id.parse::<i64>().map(|i| Subscription::Intents(Some(i))).map_err(|_| ()),
+        None => Err(()),
+    }
+}
+
+// resource names may have spaces, which are percent-encoded in path.
+fn decode(s: &str) -> Option<String> {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = std::str::from_utf8(bytes.get(i + 1..i + 3)?).ok()?;
+            out.push(u8::from_str_radix(hex, 16).ok()?);
+            i += 3;
+        } else {
+            out.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(out).ok()
+}
+
+fn is_subscribed(filter: Option<i64>, event: &IntentEvent) -> bool {
+    match filter {
+        Some(id) => event.get_intent_id() == id,
+        None => true,
+    }
+}
+
+// the error response type is given by tungstenite.
+#[allow(clippy::result_large_err)]
+async fn serve(stream: TcpStream) -> BoxResult<()> {
+    let mut subscription = Subscription::Intents(None);
+    let callback = |req: &Request, resp: Response| -> Result<Response, ErrorResponse> {
+        match parse_path(req.uri().path()) {
+            Ok(s) => {
+                subscription = s;
+                Ok(resp)
+            },
+            Err(_) => {
+                let mut e = ErrorResponse::new(Some("expect /intents, /intents/<id> or /telemetry/<name>".to_string()));
+                *e.status_mut() = StatusCode::NOT_FOUND;
+                Err(e)
+            },
+        }
+    };
+    let ws = accept_hdr_async(stream, callback).await?;
+    let filter = match subscription {
+        Subscription::Intents(f) => f,
+        Subscription::Telemetry(name) => return serve_telemetry(ws, &name).await,
+    };
+    let (mut ws_tx, mut ws_rx) = ws.split();
+    // subscribe after handshake, events before connection are not replayed.
+    let mut events = subscribe();
+
+    loop {
+        tokio::select! {
+            e = events.recv() => {
+                match e {
+                    Ok(e) => {
+                        if !is_subscribed(filter, &e) {
+                            continue;
+                        }
+                        let e_json = serde_json::to_string(&e)?;
+                        ws_tx.send(Message::Text(e_json.into())).await?;
+                    },
+                    Err(RecvError::Lagged(n)) => warn!("event stream lagged {} events", n),
+                    Err(RecvError::Closed) => break,
+                }
+            },
+            m = ws_rx.next() => {
+                match m {
+                    Some(Ok(Message::Close(_))) | None => break,
+                    Some(Ok(Message::Ping(p))) => ws_tx.send(Message::Pong(p)).await?,
+                    Some(Ok(_)) => (),
+                    Some(Err(e)) => return Err(Box::new(e)),
+                }
+            },
+        }
+    }
+    Ok(())
+}
+
+// answer every query of the client with the history of the resource.
+async fn serve_telemetry(ws: WebSocketStream<TcpStream>, name: &str) -> BoxResult<()> {
+    let (mut ws_tx, mut ws_rx) = ws.split();
+    while let Some(m) = ws_rx.next().await {
+        match m? {
+            Message::Text(t) => {
+                let window = Duration::from_secs(t.trim().parse::<u64>().unwrap_or(TELEMETRY_WINDOW));
+                let reply = {
+                    let book = TELEMETRY.lock().await;
+                    TelemetryReply {
+                        resource: name,
+                        unavailable_since: book.unavailable_since(name),
+                        aggregate: book.aggregate(name, window),
+                        samples: book.window(name, window).into_iter().cloned().collect(),
+                    }
+                };
+                ws_tx.send(Message::Text(serde_json::to_string(&reply)?.into())).await?;
+            },
+            Message::Ping(p) => ws_tx.send(Message::Pong(p)).await?,
+            Message::Close(_) => break,
+            _ => (),
+        }
+    }
+    Ok(())
+}
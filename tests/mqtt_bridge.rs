@@ -0,0 +1,101 @@
+// the mqtt bridge against a local broker, like `mosquitto -p 1883`.
+// the broker is given by TAPE_MQTT_HOST and TAPE_MQTT_PORT, the test is skipped if it is unreachable.
+
+use std::{env, time::Duration};
+use rumqttc::{AsyncClient, Event, MqttOptions, Packet, QoS};
+use tokio::{net::TcpStream, sync::mpsc, time::{sleep, timeout}};
+
+use tapeos::{
+    base::{
+        intent::{Intent, IntentSource, IntentType, SubIntent},
+        message::{Message, MessageType},
+    },
+    components::linkhub::{
+        mqtt::seek::seek_broker,
+        registry::RESOURCES,
+        seeker::{send_intent, INTENT_QUEUE},
+    },
+    tools::idgen::init_id_generator,
+};
+
+const NAME: &str = "tape-test-lamp";
+const WAIT: Duration = Duration::from_secs(10);
+
+fn broker() -> (String, u16) {
+    let host = env::var("TAPE_MQTT_HOST").unwrap_or("127.0.0.1".to_string());
+    let port = env::var("TAPE_MQTT_PORT").ok().and_then(|p| p.parse().ok()).unwrap_or(1883);
+    (host, port)
+}
+
+// description of the registered resource, None if it is not registered.
+async fn registered_description() -> Option<String> {
+    let r = RESOURCES.lock().await.get(NAME)?;
+    let d = r.lock().await.get_description().to_string();
+    Some(d)
+}
+
+// announce until TAPE knows the resource by the description, the bridge may not subscribe yet.
+async fn announce(device: &AsyncClient, description: &str) {
+    let a = format!(r#"{{"name": "{NAME}", "description": "{description}"}}"#);
+    let registered = timeout(WAIT, async {
+        loop {
+            device.publish("tape/announce", QoS::AtLeastOnce, false, a.clone()).await.unwrap();
+            sleep(Duration::from_millis(300)).await;
+            if registered_description().await.as_deref() == Some(description) {
+                break;
+            }
+        }
+    }).await;
+    assert!(registered.is_ok(), "{} is not registered as {}", NAME, description);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn announce_route_reply() {
+    let (host, port) = broker();
+    if TcpStream::connect((host.as_str(), port)).await.is_err() {
+        eprintln!("skip: no mqtt broker on {}:{}", host, port);
+        return;
+    }
+    init_id_generator();
+    let bridge_host = host.clone();
+    tokio::spawn(async move { seek_broker(&bridge_host, port).await });
+
+    // the device answer every sub-intent it gets.
+    let (device, mut eventloop) = AsyncClient::new(MqttOptions::new(NAME, host, port), 16);
+    device.subscribe(format!("tape/{NAME}/command"), QoS::AtLeastOnce).await.unwrap();
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    let replier = device.clone();
+    tokio::spawn(async move {
+        while let Ok(event) = eventloop.poll().await {
+            if let Event::Incoming(Packet::Publish(p)) = event {
+                let m: Message = serde_json::from_slice(&p.payload).unwrap();
+                let r = Message::new(MessageType::Response, "Execute Over".to_string(), m.get_id());
+                let r_json = serde_json::to_string(&r).unwrap();
+                replier.publish(format!("tape/{NAME}/result"), QoS::AtLeastOnce, false, r_json).await.unwrap();
+                tx.send(m.get_body()).unwrap();
+            }
+        }
+    });
+
+    announce(&device, "lamp").await;
+    // announce again with a new description update the resource.
+    announce(&device, "dimmable lamp").await;
+
+    let mut intent = Intent::new("turn on the lamp".to_string(), IntentSource::Input, IntentType::Intent, Some(NAME.to_string()));
+    let mut s_intent = SubIntent::new("turn on".to_string(), vec![NAME.to_string()]);
+    s_intent.set_selected_resource(NAME.to_string());
+    let (id, sub_id) = (intent.get_id(), s_intent.get_id());
+    intent.add_sub_intent(vec![s_intent]);
+    INTENT_QUEUE.lock().await.push(intent);
+    send_intent(NAME.to_string(), "turn on", sub_id).await.unwrap();
+
+    let body = timeout(WAIT, rx.recv()).await.expect("no command is routed to the device");
+    assert_eq!(body.as_deref(), Some("turn on"));
+    let completed = timeout(WAIT, async {
+        while INTENT_QUEUE.lock().await.iter().any(|i| i.get_id() == id) {
+            sleep(Duration::from_millis(100)).await;
+        }
+    }).await;
+    assert!(completed.is_ok(), "the reply does not complete the intent");
+}
+